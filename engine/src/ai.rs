@@ -0,0 +1,63 @@
+//! Computer players. [`Expectimax`] searches on a [`Bitboard`], so it only
+//! plays the classic rules on a 4x4 board. [`MonteCarlo`] plays out whole
+//! games on the grid itself, so it handles any square board and rule.
+//! [`NTupleNetwork`] is a value function learned by self-play for the classic
+//! 4x4 game.
+
+pub mod expectimax;
+pub mod heuristic;
+pub mod monte_carlo;
+pub mod ntuple;
+
+pub use expectimax::{Expectimax, LOST};
+pub use heuristic::{CornerWeighting, EmptyCells, Heuristic, Monotonicity, Smoothness, Weighted};
+pub use monte_carlo::{MonteCarlo, RolloutPolicy};
+pub use ntuple::{NTupleNetwork, Training, WeightsError};
+
+use std::fmt::Debug;
+
+use crate::bitboard::{Bitboard, MAX_TILE};
+use crate::direction::MoveDirection;
+use crate::grid::Grid;
+use crate::shape::Shape;
+use crate::spawn::SpawnRules;
+
+/// A player that picks moves on a square board.
+pub trait Agent: Debug + Send + Sync {
+    /// The move to play next, or `None` if there is no legal move or the
+    /// agent cannot play this grid.
+    fn best_move(&self, grid: &Grid) -> Option<MoveDirection>;
+}
+
+/// Packs `grid` into a [`Bitboard`] if it is a classic 4x4 game without walls.
+pub fn classic_board(grid: &Grid) -> Option<Bitboard> {
+    let square = Shape::Rectangle {
+        rows: 4,
+        columns: 4,
+    };
+    if grid.shape() != square || !grid.merge_rule.is_classic() {
+        return None;
+    }
+    return Bitboard::from_state(&grid.state);
+}
+
+/// Spawn odds as `(exponent, probability)` pairs. Returns `None` if a value
+/// cannot be stored on a [`Bitboard`] or no value can spawn.
+pub fn spawn_odds(rules: &SpawnRules) -> Option<Vec<(u32, f64)>> {
+    let total: u32 = rules.values.iter().map(|(_, weight)| weight).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut odds = vec![];
+    for (value, weight) in rules.values.iter() {
+        if *weight == 0 {
+            continue;
+        }
+        let value = u32::try_from(*value).ok()?;
+        if value < 2 || !value.is_power_of_two() || value > MAX_TILE {
+            return None;
+        }
+        odds.push((value.trailing_zeros(), *weight as f64 / total as f64));
+    }
+    return Some(odds);
+}
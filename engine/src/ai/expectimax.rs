@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use crate::ai::heuristic::{Heuristic, Weighted};
+use crate::ai::{classic_board, spawn_odds, Agent};
+use crate::bitboard::Bitboard;
+use crate::direction::{MoveDirection, DIRECTIONS};
+use crate::grid::Grid;
+
+/// Value of a lost board. Far below anything a heuristic should return, so
+/// the search always prefers staying alive, yet finite so that chance nodes
+/// can still weigh how likely a loss is. A board lost with `n` moves still to
+/// search scores `n + 1` times this, so a loss that cannot be avoided is put
+/// off as long as possible.
+pub const LOST: f64 = -1.0e15;
+
+/// Expectimax search. Move nodes take the best direction and chance nodes
+/// average over every cell and value a tile could spawn with.
+///
+/// Each spawn is assumed to be a single tile on any empty cell, whatever the
+/// grid's spawn rules say about tiles per move and edges.
+#[derive(Debug, Clone)]
+pub struct Expectimax {
+    /// Moves to look ahead.
+    pub depth: u32,
+    /// Chance nodes reached with a lower probability than this are not
+    /// expanded and are scored by the heuristic instead.
+    pub probability_cutoff: f64,
+    /// Scores the boards at the edge of the search. Lost boards score
+    /// [`LOST`] or lower whatever it says.
+    pub heuristic: Box<dyn Heuristic>,
+}
+
+impl Default for Expectimax {
+    fn default() -> Self {
+        return Expectimax {
+            depth: 3,
+            probability_cutoff: 0.0001,
+            heuristic: Box::new(Weighted::standard()),
+        };
+    }
+}
+
+impl Agent for Expectimax {
+    /// The direction with the highest expected value, or `None` if there is no
+    /// legal move or the grid is not a classic 4x4 game.
+    fn best_move(&self, grid: &Grid) -> Option<MoveDirection> {
+        let board = classic_board(grid)?;
+        let odds = spawn_odds(&grid.spawn_rules)?;
+        return self.best_board_move(board, &odds);
+    }
+}
+
+impl Expectimax {
+    /// The best direction on `board` when tiles spawn with `odds`, as given by
+    /// [`spawn_odds`]. Useful when the search runs away from the grid, such as
+    /// on another thread.
+    pub fn best_board_move(&self, board: Bitboard, odds: &[(u32, f64)]) -> Option<MoveDirection> {
+        let mut search = Search {
+            config: self,
+            odds,
+            cache: HashMap::new(),
+        };
+
+        let mut best: Option<(MoveDirection, f64)> = None;
+        for direction in DIRECTIONS {
+            let (moved, _) = board.move_in_dir(direction);
+            if moved == board {
+                continue;
+            }
+            let value = search.chance_node(moved, self.depth, 1.0);
+            let better = match best {
+                Some((_, best_value)) => value > best_value,
+                None => true,
+            };
+            if better {
+                best = Some((direction, value));
+            }
+        }
+        return best.map(|(direction, _)| direction);
+    }
+}
+
+struct Search<'a> {
+    config: &'a Expectimax,
+    odds: &'a [(u32, f64)],
+    /// Values of chance nodes already searched, with the depth they were
+    /// searched to.
+    cache: HashMap<Bitboard, (u32, f64)>,
+}
+
+impl Search<'_> {
+    fn move_node(&mut self, board: Bitboard, depth: u32, probability: f64) -> f64 {
+        if board.is_deadlocked() {
+            return LOST * (depth + 1) as f64;
+        }
+        if depth == 0 {
+            return self.config.heuristic.evaluate(board);
+        }
+        let mut best = LOST * (depth + 1) as f64;
+        for direction in DIRECTIONS {
+            let (moved, _) = board.move_in_dir(direction);
+            if moved != board {
+                best = f64::max(best, self.chance_node(moved, depth, probability));
+            }
+        }
+        return best;
+    }
+
+    /// Expected value of `board` once a tile spawns, with `depth` moves left
+    /// to search. With no moves left it is scored by the heuristic as it
+    /// stands.
+    fn chance_node(&mut self, board: Bitboard, depth: u32, probability: f64) -> f64 {
+        if depth == 0 || probability < self.config.probability_cutoff {
+            return self.config.heuristic.evaluate(board);
+        }
+        if let Some((searched, value)) = self.cache.get(&board) {
+            if *searched >= depth {
+                return *value;
+            }
+        }
+
+        let cells: Vec<(usize, usize)> = (0..4)
+            .flat_map(|i| (0..4).map(move |j| (i, j)))
+            .filter(|(i, j)| board.exponent(*i, *j) == 0)
+            .collect();
+        if cells.is_empty() {
+            return self.move_node(board, depth - 1, probability);
+        }
+
+        let odds = self.odds;
+        let mut total = 0.0;
+        for (i, j) in cells.iter() {
+            for (exponent, chance) in odds.iter() {
+                let spawned = board.set(*i, *j, 1 << exponent);
+                let chance = chance / cells.len() as f64;
+                total += chance * self.move_node(spawned, depth - 1, probability * chance);
+            }
+        }
+
+        self.cache.insert(board, (depth, total));
+        return total;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::ai::heuristic::{Monotonicity, Smoothness};
+    use crate::rng::GridRng;
+
+    const ODDS: [(u32, f64); 2] = [(1, 0.9), (2, 0.1)];
+
+    /// Whether every spawn after `direction` leaves a board with no moves.
+    fn loses_immediately(board: Bitboard, direction: MoveDirection) -> bool {
+        let (moved, _) = board.move_in_dir(direction);
+        for i in 0..4 {
+            for j in 0..4 {
+                if moved.exponent(i, j) != 0 {
+                    continue;
+                }
+                for (exponent, _) in ODDS {
+                    if !moved.set(i, j, 1 << exponent).is_deadlocked() {
+                        return false;
+                    }
+                }
+            }
+        }
+        return true;
+    }
+
+    /// Nearly full boards where some moves lose straight away and others do
+    /// not.
+    fn risky_boards(count: usize) -> Vec<Bitboard> {
+        let mut rng = GridRng::from_seed(21);
+        let mut boards = vec![];
+        while boards.len() < count {
+            let mut board = Bitboard(0);
+            for i in 0..4 {
+                for j in 0..4 {
+                    board = board.set(i, j, 1 << rng.gen_range(1..=11));
+                }
+            }
+            for _ in 0..rng.gen_range(1..=2) {
+                board = board.set(rng.gen_range(0..4), rng.gen_range(0..4), 0);
+            }
+            let legal: Vec<MoveDirection> = DIRECTIONS
+                .into_iter()
+                .filter(|direction| board.move_in_dir(*direction).0 != board)
+                .collect();
+            let losing = legal
+                .iter()
+                .filter(|direction| loses_immediately(board, **direction))
+                .count();
+            if losing > 0 && losing < legal.len() {
+                boards.push(board);
+            }
+        }
+        return boards;
+    }
+
+    fn assert_avoids_immediate_losses(expectimax: &Expectimax) {
+        for board in risky_boards(100) {
+            let direction = expectimax.best_board_move(board, &ODDS).unwrap();
+            assert!(
+                !loses_immediately(board, direction),
+                "{direction:?} loses straight away on {:?}",
+                board.to_state()
+            );
+        }
+    }
+
+    #[test]
+    fn default_never_picks_a_move_that_loses_immediately() {
+        assert_avoids_immediate_losses(&Expectimax::default());
+    }
+
+    #[test]
+    fn heuristics_that_never_score_above_zero_still_avoid_losing() {
+        for heuristic in [
+            Box::new(Monotonicity::default()) as Box<dyn Heuristic>,
+            Box::new(Smoothness),
+        ] {
+            assert_avoids_immediate_losses(&Expectimax {
+                heuristic,
+                ..Default::default()
+            });
+        }
+    }
+
+    #[test]
+    fn depth_zero_picks_the_move_the_heuristic_likes_best() {
+        let expectimax = Expectimax {
+            depth: 0,
+            ..Default::default()
+        };
+        for board in risky_boards(20) {
+            let direction = expectimax.best_board_move(board, &ODDS).unwrap();
+            let value = |direction: MoveDirection| {
+                expectimax
+                    .heuristic
+                    .evaluate(board.move_in_dir(direction).0)
+            };
+            for other in DIRECTIONS {
+                if board.move_in_dir(other).0 != board {
+                    assert!(value(direction) >= value(other));
+                }
+            }
+        }
+        assert_eq!(expectimax.best_board_move(Bitboard(0), &ODDS), None);
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt::Debug;
+
+use crate::bitboard::Bitboard;
+
+/// Rates how promising a board is. Higher is better.
+pub trait Heuristic: Debug + Send + Sync {
+    fn evaluate(&self, board: Bitboard) -> f64;
+
+    fn clone_box(&self) -> Box<dyn Heuristic>;
+}
+
+impl Clone for Box<dyn Heuristic> {
+    fn clone(&self) -> Self {
+        return self.clone_box();
+    }
+}
+
+/// Exponents of every row followed by every column, each read left to right
+/// or top to bottom.
+fn lines(board: Bitboard) -> [[u32; 4]; 8] {
+    let mut lines = [[0; 4]; 8];
+    for i in 0..4 {
+        for j in 0..4 {
+            let exponent = board.exponent(i, j);
+            lines[i][j] = exponent;
+            lines[4 + j][i] = exponent;
+        }
+    }
+    return lines;
+}
+
+/// Number of empty cells. Space to manoeuvre is what keeps a game alive.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmptyCells;
+
+impl Heuristic for EmptyCells {
+    fn evaluate(&self, board: Bitboard) -> f64 {
+        return board.empty_cells() as f64;
+    }
+
+    fn clone_box(&self) -> Box<dyn Heuristic> {
+        return Box::new(*self);
+    }
+}
+
+/// Penalises rows and columns that do not rise or fall steadily. Each line
+/// counts the smaller of its rises and falls, with exponents raised to
+/// `power` so that disorder among big tiles costs most.
+#[derive(Debug, Clone, Copy)]
+pub struct Monotonicity {
+    pub power: i32,
+}
+
+impl Default for Monotonicity {
+    fn default() -> Self {
+        return Monotonicity { power: 4 };
+    }
+}
+
+impl Heuristic for Monotonicity {
+    fn evaluate(&self, board: Bitboard) -> f64 {
+        let mut penalty = 0.0;
+        for line in lines(board) {
+            let (mut rises, mut falls) = (0.0, 0.0);
+            for pair in line.windows(2) {
+                let a = (pair[0] as f64).powi(self.power);
+                let b = (pair[1] as f64).powi(self.power);
+                if a < b {
+                    rises += b - a;
+                } else {
+                    falls += a - b;
+                }
+            }
+            penalty += f64::min(rises, falls);
+        }
+        return -penalty;
+    }
+
+    fn clone_box(&self) -> Box<dyn Heuristic> {
+        return Box::new(*self);
+    }
+}
+
+/// Penalises neighbouring tiles whose exponents differ, since only equal
+/// tiles can merge. Empty cells are skipped, so tiles either side of a gap
+/// count as neighbours.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Smoothness;
+
+impl Heuristic for Smoothness {
+    fn evaluate(&self, board: Bitboard) -> f64 {
+        let mut penalty = 0.0;
+        for line in lines(board) {
+            let tiles: Vec<u32> = line.into_iter().filter(|exponent| *exponent != 0).collect();
+            for pair in tiles.windows(2) {
+                penalty += pair[0].abs_diff(pair[1]) as f64;
+            }
+        }
+        return -penalty;
+    }
+
+    fn clone_box(&self) -> Box<dyn Heuristic> {
+        return Box::new(*self);
+    }
+}
+
+/// Rewards big tiles near a corner. Each tile counts its value, halved for
+/// every step away from the corner, and the best corner is used.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CornerWeighting;
+
+impl Heuristic for CornerWeighting {
+    fn evaluate(&self, board: Bitboard) -> f64 {
+        let mut best = 0.0;
+        for (ci, cj) in [(0, 0), (0, 3), (3, 0), (3, 3)] {
+            let mut total = 0.0;
+            for i in 0..4usize {
+                for j in 0..4usize {
+                    let distance = i.abs_diff(ci) + j.abs_diff(cj);
+                    total += board.get(i, j) as f64 / (1 << distance) as f64;
+                }
+            }
+            best = f64::max(best, total);
+        }
+        return best;
+    }
+
+    fn clone_box(&self) -> Box<dyn Heuristic> {
+        return Box::new(*self);
+    }
+}
+
+/// A weighted sum of other heuristics on top of a constant `baseline`.
+/// The baseline only shifts the values the search reports.
+#[derive(Debug, Clone)]
+pub struct Weighted {
+    pub baseline: f64,
+    pub terms: Vec<(f64, Box<dyn Heuristic>)>,
+}
+
+impl Weighted {
+    /// All four built-in heuristics with weights that play well together.
+    pub fn standard() -> Self {
+        return Weighted {
+            baseline: 200_000.0,
+            terms: vec![
+                (270.0, Box::new(EmptyCells)),
+                (47.0, Box::new(Monotonicity::default())),
+                (100.0, Box::new(Smoothness)),
+                (1.0, Box::new(CornerWeighting)),
+            ],
+        };
+    }
+}
+
+impl Default for Weighted {
+    fn default() -> Self {
+        return Weighted::standard();
+    }
+}
+
+impl Heuristic for Weighted {
+    fn evaluate(&self, board: Bitboard) -> f64 {
+        return self.baseline
+            + self
+                .terms
+                .iter()
+                .map(|(weight, heuristic)| weight * heuristic.evaluate(board))
+                .sum::<f64>();
+    }
+
+    fn clone_box(&self) -> Box<dyn Heuristic> {
+        return Box::new(self.clone());
+    }
+}
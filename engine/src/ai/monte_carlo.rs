@@ -0,0 +1,215 @@
+use std::collections::HashSet;
+use std::thread;
+
+use rand::seq::SliceRandom;
+
+use crate::ai::Agent;
+use crate::direction::MoveDirection;
+use crate::grid::{Grid, Tile};
+use crate::rng::GridRng;
+use crate::shape::Shape;
+
+/// How moves are chosen during a rollout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RolloutPolicy {
+    /// Any legal move, picked uniformly.
+    Random,
+    /// The legal move that scores most right away, with ties broken at
+    /// random.
+    Greedy,
+}
+
+/// Plays each legal move followed by many games to the end, and picks the
+/// move whose games finish with the best average score.
+///
+/// Rollouts play on forks of the grid with [`Grid::update`], so they follow
+/// the grid's own merge and spawn rules. They are spread over `threads`
+/// threads. Each rollout is seeded from `seed`, so the same grid always gets
+/// the same answer.
+#[derive(Debug, Clone)]
+pub struct MonteCarlo {
+    /// Rollouts per legal move. `0` is treated as `1`, since a move picked
+    /// without any rollout would be arbitrary.
+    pub rollouts: u32,
+    pub policy: RolloutPolicy,
+    pub threads: usize,
+    pub seed: u64,
+}
+
+impl Default for MonteCarlo {
+    fn default() -> Self {
+        return MonteCarlo {
+            rollouts: 100,
+            policy: RolloutPolicy::Random,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            seed: 0,
+        };
+    }
+}
+
+impl MonteCarlo {
+    /// Plays `direction` and then the rollout policy until the game is over,
+    /// and returns the final score.
+    ///
+    /// Spawn rules that place nothing leave a game that never ends, with tiles
+    /// sliding back and forth forever. So once moves stop spawning tiles, the
+    /// rollout also ends when it comes back to a board it has already seen.
+    fn rollout(&self, grid: &Grid, direction: MoveDirection, seed: u64) -> u32 {
+        let mut grid = grid.fork(seed);
+        let mut rng = GridRng::from_seed(!seed);
+        let mut unspawned: HashSet<Vec<Vec<Tile>>> = HashSet::new();
+        grid.update(direction);
+        while !grid.deadlocked {
+            let moves: Vec<MoveDirection> = grid.legal_moves().collect();
+            let chosen = match self.policy {
+                RolloutPolicy::Random => moves.choose(&mut rng).copied(),
+                RolloutPolicy::Greedy => {
+                    let gains: Vec<u32> = moves
+                        .iter()
+                        .map(|direction| grid.preview(*direction).map_or(0, |p| p.score_delta))
+                        .collect();
+                    let best = gains.iter().copied().max().unwrap_or(0);
+                    let greediest: Vec<MoveDirection> = moves
+                        .iter()
+                        .zip(gains)
+                        .filter(|(_, gain)| *gain == best)
+                        .map(|(direction, _)| *direction)
+                        .collect();
+                    greediest.choose(&mut rng).copied()
+                }
+            };
+            let Some(chosen) = chosen else {
+                break;
+            };
+            if !grid.update(chosen).spawns.is_empty() {
+                unspawned.clear();
+            } else if !unspawned.insert(grid.state.clone()) {
+                break;
+            }
+        }
+        return grid.score;
+    }
+}
+
+impl Agent for MonteCarlo {
+    /// The move with the best average rollout score, or `None` if there is no
+    /// legal move or the board is not square.
+    fn best_move(&self, grid: &Grid) -> Option<MoveDirection> {
+        if !matches!(grid.shape(), Shape::Rectangle { .. } | Shape::Torus { .. }) {
+            return None;
+        }
+        let moves: Vec<MoveDirection> = grid.legal_moves().collect();
+        if moves.len() <= 1 {
+            return moves.first().copied();
+        }
+
+        // Every (move, rollout) pair is a job, seeded by its index.
+        let rollouts = self.rollouts.max(1) as u64;
+        let jobs: Vec<(usize, u64)> = (0..moves.len())
+            .flat_map(|index| (0..rollouts).map(move |rollout| (index, rollout)))
+            .enumerate()
+            .map(|(job, (index, _))| {
+                (
+                    index,
+                    self.seed
+                        .wrapping_add(job as u64)
+                        .wrapping_mul(0x9E37_79B9_7F4A_7C15),
+                )
+            })
+            .collect();
+        let chunk_size = jobs.len().div_ceil(self.threads.max(1)).max(1);
+
+        let mut totals = vec![0u64; moves.len()];
+        thread::scope(|scope| {
+            let workers: Vec<_> = jobs
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let moves = &moves;
+                    return scope.spawn(move || {
+                        let mut totals = vec![0u64; moves.len()];
+                        for (index, seed) in chunk {
+                            totals[*index] += self.rollout(grid, moves[*index], *seed) as u64;
+                        }
+                        return totals;
+                    });
+                })
+                .collect();
+            for worker in workers {
+                let partial = worker.join().expect("rollout thread panicked");
+                for (total, score) in totals.iter_mut().zip(partial) {
+                    *total += score;
+                }
+            }
+        });
+
+        // Every move has the same number of rollouts, so the best total is
+        // the best average.
+        let best = (0..moves.len()).max_by_key(|index| totals[*index])?;
+        return Some(moves[best]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn started_grid(seed: u64) -> Grid {
+        let mut grid = Grid::with_seed(4, 4, seed);
+        grid.reset_with_seed(seed);
+        return grid;
+    }
+
+    #[test]
+    fn rollouts_end_when_nothing_spawns() {
+        let mut no_tiles = started_grid(3);
+        no_tiles.spawn_rules.tiles_per_move = 0;
+        let mut no_weights = started_grid(3);
+        for (_, weight) in no_weights.spawn_rules.values.iter_mut() {
+            *weight = 0;
+        }
+
+        for policy in [RolloutPolicy::Random, RolloutPolicy::Greedy] {
+            let agent = MonteCarlo {
+                rollouts: 4,
+                policy,
+                threads: 1,
+                seed: 0,
+            };
+            for grid in [&no_tiles, &no_weights] {
+                assert!(agent.best_move(grid).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_move() {
+        let agent = MonteCarlo {
+            rollouts: 8,
+            threads: 2,
+            ..Default::default()
+        };
+        for seed in 0..4 {
+            let grid = started_grid(seed);
+            assert_eq!(agent.best_move(&grid), agent.best_move(&grid));
+        }
+    }
+
+    #[test]
+    fn zero_rollouts_play_one() {
+        let agent = |rollouts| MonteCarlo {
+            rollouts,
+            threads: 1,
+            ..Default::default()
+        };
+        let mut not_last = 0;
+        for seed in 0..20 {
+            let grid = started_grid(seed);
+            let chosen = agent(0).best_move(&grid);
+            assert_eq!(chosen, agent(1).best_move(&grid));
+            if chosen != grid.legal_moves().last() {
+                not_last += 1;
+            }
+        }
+        assert!(not_last > 0);
+    }
+}
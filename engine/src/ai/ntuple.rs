@@ -0,0 +1,498 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::ai::{classic_board, Agent};
+use crate::bitboard::Bitboard;
+use crate::direction::{MoveDirection, DIRECTIONS};
+use crate::grid::Grid;
+
+/// First bytes of every weights file.
+const MAGIC: [u8; 8] = *b"2048NTUP";
+/// Bumped whenever the layout of a weights file changes. Older files are
+/// rejected rather than misread.
+pub const WEIGHTS_VERSION: u32 = 1;
+/// Longest tuple a network may hold. A tuple of `n` cells has `16^n`
+/// weights, which is already 64 MiB at this length.
+pub const MAX_TUPLE_LENGTH: usize = 6;
+
+/// A value function for the classic 4x4 game, learned by
+/// [`Training`]. Each tuple is a group of cells, numbered row by row from
+/// `0` to `15`, with one weight for every combination of exponents they can
+/// hold. A board's value is the sum of the weights its tuples pick out, with
+/// every tuple also read under the eight rotations and reflections of the
+/// board.
+///
+/// As an [`Agent`] it plays the move whose reward plus value of the board
+/// left behind, before the spawn, is highest.
+#[derive(Clone)]
+pub struct NTupleNetwork {
+    tuples: Vec<Vec<usize>>,
+    /// Each tuple's cells under every symmetry of the board.
+    symmetries: Vec<Vec<Vec<usize>>>,
+    weights: Vec<Vec<f32>>,
+}
+
+impl NTupleNetwork {
+    /// A network with every weight at `0`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a tuple is empty, longer than [`MAX_TUPLE_LENGTH`] or holds a
+    /// cell outside the board.
+    pub fn new(tuples: Vec<Vec<usize>>) -> Self {
+        for tuple in tuples.iter() {
+            assert!(
+                valid_tuple(tuple),
+                "invalid tuple {tuple:?}: tuples need 1 to {MAX_TUPLE_LENGTH} cells from 0 to 15"
+            );
+        }
+        let weights = tuples
+            .iter()
+            .map(|tuple| vec![0.0; 1 << (4 * tuple.len())])
+            .collect();
+        let symmetries = tuples.iter().map(|tuple| symmetries(tuple)).collect();
+        return NTupleNetwork {
+            tuples,
+            symmetries,
+            weights,
+        };
+    }
+
+    /// Two straight tuples along the edge and the second row, and three 2x2
+    /// squares. Small enough to train in minutes and save in about a
+    /// megabyte.
+    pub fn standard() -> Self {
+        return NTupleNetwork::new(vec![
+            vec![0, 1, 2, 3],
+            vec![4, 5, 6, 7],
+            vec![0, 1, 4, 5],
+            vec![1, 2, 5, 6],
+            vec![5, 6, 9, 10],
+        ]);
+    }
+
+    pub fn tuples(&self) -> &[Vec<usize>] {
+        return &self.tuples;
+    }
+
+    pub fn evaluate(&self, board: Bitboard) -> f32 {
+        let mut value = 0.0;
+        for (symmetries, weights) in self.symmetries.iter().zip(self.weights.iter()) {
+            for cells in symmetries.iter() {
+                value += weights[index(board, cells)];
+            }
+        }
+        return value;
+    }
+
+    /// Adds `delta` to every weight `board` picks out.
+    fn adjust(&mut self, board: Bitboard, delta: f32) {
+        for (symmetries, weights) in self.symmetries.iter().zip(self.weights.iter_mut()) {
+            for cells in symmetries.iter() {
+                weights[index(board, cells)] += delta;
+            }
+        }
+    }
+
+    /// The legal move with the highest reward plus value, together with the
+    /// board it leaves and its reward.
+    fn choose(&self, board: Bitboard) -> Option<(MoveDirection, Bitboard, u32)> {
+        let mut best: Option<(MoveDirection, Bitboard, u32, f32)> = None;
+        for direction in DIRECTIONS {
+            let (moved, reward) = board.move_in_dir(direction);
+            if moved == board {
+                continue;
+            }
+            let value = reward as f32 + self.evaluate(moved);
+            let better = match best {
+                Some((_, _, _, best_value)) => value > best_value,
+                None => true,
+            };
+            if better {
+                best = Some((direction, moved, reward, value));
+            }
+        }
+        return best.map(|(direction, moved, reward, _)| (direction, moved, reward));
+    }
+
+    /// Reads the network out of a weights file written by [`Training::save`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, WeightsError> {
+        return Ok(Training::load(path)?.network);
+    }
+}
+
+impl Default for NTupleNetwork {
+    fn default() -> Self {
+        return NTupleNetwork::standard();
+    }
+}
+
+/// Leaves out the weights, which run to hundreds of thousands.
+impl fmt::Debug for NTupleNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NTupleNetwork")
+            .field("tuples", &self.tuples)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Agent for NTupleNetwork {
+    /// The move with the highest reward plus value, or `None` if there is no
+    /// legal move or the grid is not a classic 4x4 game.
+    fn best_move(&self, grid: &Grid) -> Option<MoveDirection> {
+        let board = classic_board(grid)?;
+        return self.choose(board).map(|(direction, _, _)| direction);
+    }
+}
+
+fn valid_tuple(tuple: &[usize]) -> bool {
+    return !tuple.is_empty()
+        && tuple.len() <= MAX_TUPLE_LENGTH
+        && tuple.iter().all(|cell| *cell < 16);
+}
+
+/// `tuple` under the four rotations of the board, each with and without a
+/// mirror image.
+fn symmetries(tuple: &[usize]) -> Vec<Vec<usize>> {
+    let mut symmetries = vec![];
+    let mut cells = tuple.to_vec();
+    for _ in 0..4 {
+        // Turn a quarter clockwise: (i, j) goes to (j, 3 - i).
+        cells = cells
+            .iter()
+            .map(|cell| 4 * (cell % 4) + (3 - cell / 4))
+            .collect();
+        symmetries.push(cells.clone());
+        // Mirror left to right: (i, j) goes to (i, 3 - j).
+        symmetries.push(
+            cells
+                .iter()
+                .map(|cell| cell - cell % 4 + (3 - cell % 4))
+                .collect(),
+        );
+    }
+    return symmetries;
+}
+
+/// The weight `cells` pick out on `board`: their exponents read as the digits
+/// of a base 16 number.
+fn index(board: Bitboard, cells: &[usize]) -> usize {
+    let mut index = 0;
+    for (m, cell) in cells.iter().enumerate() {
+        index |= (((board.0 >> (4 * cell)) & 0xF) as usize) << (4 * m);
+    }
+    return index;
+}
+
+/// An [`NTupleNetwork`] part way through temporal difference learning by
+/// self-play, with everything needed to carry on exactly where it stopped.
+///
+/// Game `n` is dealt from a seed made of `seed` and `n`, and the network
+/// only learns from the games it plays, so a run is fully determined by its
+/// seed and learning rate. Stopping, saving, loading and training on lands on
+/// the same weights as training straight through.
+#[derive(Debug, Clone)]
+pub struct Training {
+    pub network: NTupleNetwork,
+    pub seed: u64,
+    pub learning_rate: f32,
+    /// Games played so far.
+    pub games: u64,
+}
+
+impl Training {
+    pub fn new(network: NTupleNetwork, seed: u64, learning_rate: f32) -> Self {
+        return Training {
+            network,
+            seed,
+            learning_rate,
+            games: 0,
+        };
+    }
+
+    /// Seed for the spawns of game `game`.
+    pub fn game_seed(&self, game: u64) -> u64 {
+        return self.seed.rotate_left(32) ^ game;
+    }
+
+    /// Plays one game with the network's own moves and returns the finished
+    /// grid.
+    ///
+    /// Learning uses the boards left after each move, before the spawn. Each
+    /// one's value is moved towards the reward of the next move plus the value
+    /// of the board that move leaves, and the last one's towards `0`.
+    pub fn play_game(&mut self) -> Grid {
+        let mut grid = Grid::with_seed(4, 4, self.game_seed(self.games));
+        grid.add_starting_boxes();
+
+        let mut previous: Option<Bitboard> = None;
+        while let Some(board) = classic_board(&grid) {
+            let Some((direction, moved, reward)) = self.network.choose(board) else {
+                break;
+            };
+            if let Some(previous) = previous {
+                let target = reward as f32 + self.network.evaluate(moved);
+                self.learn(previous, target);
+            }
+            previous = Some(moved);
+            grid.update(direction);
+        }
+        if let Some(previous) = previous {
+            self.learn(previous, 0.0);
+        }
+
+        self.games += 1;
+        return grid;
+    }
+
+    fn learn(&mut self, board: Bitboard, target: f32) {
+        let error = target - self.network.evaluate(board);
+        self.network.adjust(board, self.learning_rate * error);
+    }
+
+    /// Writes a weights file. The file is written beside `path` first and
+    /// then moved into place, so an interrupted save never leaves a broken
+    /// checkpoint behind.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), WeightsError> {
+        let path = path.as_ref();
+        let partial = path.with_extension("partial");
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&partial, path)?;
+        return Ok(());
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, WeightsError> {
+        return Training::read_from(&mut BufReader::new(File::open(path)?));
+    }
+
+    /// Writes the file layout: the magic bytes and [`WEIGHTS_VERSION`], the
+    /// seed, learning rate and games played, the tuples, and then every
+    /// tuple's weights. Numbers are little endian.
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<(), WeightsError> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&WEIGHTS_VERSION.to_le_bytes())?;
+        writer.write_all(&self.seed.to_le_bytes())?;
+        writer.write_all(&self.learning_rate.to_le_bytes())?;
+        writer.write_all(&self.games.to_le_bytes())?;
+        writer.write_all(&(self.network.tuples.len() as u32).to_le_bytes())?;
+        for tuple in self.network.tuples.iter() {
+            writer.write_all(&[tuple.len() as u8])?;
+            for cell in tuple.iter() {
+                writer.write_all(&[*cell as u8])?;
+            }
+        }
+        for weights in self.network.weights.iter() {
+            for weight in weights.iter() {
+                writer.write_all(&weight.to_le_bytes())?;
+            }
+        }
+        return Ok(());
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> Result<Self, WeightsError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(WeightsError::NotWeights);
+        }
+        let version = u32::from_le_bytes(read_bytes(reader)?);
+        if version != WEIGHTS_VERSION {
+            return Err(WeightsError::Version(version));
+        }
+        let seed = u64::from_le_bytes(read_bytes(reader)?);
+        let learning_rate = f32::from_le_bytes(read_bytes(reader)?);
+        let games = u64::from_le_bytes(read_bytes(reader)?);
+
+        let count = u32::from_le_bytes(read_bytes(reader)?);
+        let mut tuples = vec![];
+        for _ in 0..count {
+            let [length] = read_bytes(reader)?;
+            let mut tuple = vec![0; length as usize];
+            reader.read_exact(&mut tuple)?;
+            let tuple: Vec<usize> = tuple.into_iter().map(usize::from).collect();
+            if !valid_tuple(&tuple) {
+                return Err(WeightsError::Tuple(tuple));
+            }
+            tuples.push(tuple);
+        }
+
+        // Weights are only kept as they arrive, so a header promising more
+        // than the file holds fails without allocating for all of it.
+        let mut weights = vec![];
+        for tuple in tuples.iter() {
+            let length = 4u64 << (4 * tuple.len());
+            let mut bytes = vec![];
+            reader.take(length).read_to_end(&mut bytes)?;
+            if bytes.len() as u64 != length {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            weights.push(
+                bytes
+                    .chunks_exact(4)
+                    .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                    .collect(),
+            );
+        }
+        let network = NTupleNetwork {
+            symmetries: tuples.iter().map(|tuple| symmetries(tuple)).collect(),
+            tuples,
+            weights,
+        };
+        if reader.read(&mut [0])? != 0 {
+            return Err(WeightsError::TrailingData);
+        }
+
+        return Ok(Training {
+            network,
+            seed,
+            learning_rate,
+            games,
+        });
+    }
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    return Ok(bytes);
+}
+
+#[derive(Debug)]
+pub enum WeightsError {
+    /// Includes files that end early.
+    Io(io::Error),
+    NotWeights,
+    /// A file written for another layout.
+    Version(u32),
+    Tuple(Vec<usize>),
+    TrailingData,
+}
+
+impl From<io::Error> for WeightsError {
+    fn from(error: io::Error) -> Self {
+        return WeightsError::Io(error);
+    }
+}
+
+impl fmt::Display for WeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeightsError::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+                write!(f, "The weights file ends early")
+            }
+            WeightsError::Io(error) => write!(f, "{error}"),
+            WeightsError::NotWeights => write!(f, "Not an n-tuple weights file"),
+            WeightsError::Version(version) => write!(
+                f,
+                "Weights file version {version} is not supported, expected {WEIGHTS_VERSION}"
+            ),
+            WeightsError::Tuple(tuple) => write!(f, "Invalid tuple {tuple:?}"),
+            WeightsError::TrailingData => write!(f, "Unexpected data after the weights"),
+        }
+    }
+}
+
+impl std::error::Error for WeightsError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trained(games: u64) -> Training {
+        let mut training = Training::new(NTupleNetwork::standard(), 25, 0.0025);
+        for _ in 0..games {
+            training.play_game();
+        }
+        return training;
+    }
+
+    fn bytes(training: &Training) -> Vec<u8> {
+        let mut bytes = vec![];
+        training.write_to(&mut bytes).unwrap();
+        return bytes;
+    }
+
+    fn assert_same(a: &Training, b: &Training) {
+        assert_eq!(a.seed, b.seed);
+        assert_eq!(a.learning_rate, b.learning_rate);
+        assert_eq!(a.games, b.games);
+        assert_eq!(a.network.tuples, b.network.tuples);
+        assert!(a.network.weights == b.network.weights);
+    }
+
+    #[test]
+    fn save_and_load_keep_every_weight() {
+        let training = trained(5);
+        assert!(training.network.weights.iter().flatten().any(|w| *w != 0.0));
+
+        let loaded = Training::read_from(&mut bytes(&training).as_slice()).unwrap();
+        assert_same(&training, &loaded);
+
+        let path = std::env::temp_dir().join(format!("ntuple-test-{}.bin", std::process::id()));
+        training.save(&path).unwrap();
+        let loaded = Training::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_same(&training, &loaded.unwrap());
+    }
+
+    #[test]
+    fn resuming_lands_on_the_same_weights() {
+        let straight = trained(8);
+
+        let mut resumed = Training::read_from(&mut bytes(&trained(3)).as_slice()).unwrap();
+        while resumed.games < 8 {
+            resumed.play_game();
+        }
+        assert_same(&straight, &resumed);
+        assert_eq!(bytes(&straight), bytes(&resumed));
+    }
+
+    #[test]
+    fn seeds_decide_the_games() {
+        let mut other = Training::new(NTupleNetwork::standard(), 26, 0.0025);
+        other.play_game();
+        assert_eq!(bytes(&trained(1)), bytes(&trained(1)));
+        assert!(trained(1).network.weights != other.network.weights);
+    }
+
+    #[test]
+    fn bad_files_are_rejected() {
+        let good = bytes(&Training::new(NTupleNetwork::new(vec![vec![0]]), 0, 0.1));
+        assert!(Training::read_from(&mut good.as_slice()).is_ok());
+
+        let mut magic = good.clone();
+        magic[0] = b'X';
+        let mut version = good.clone();
+        version[8..12].copy_from_slice(&(WEIGHTS_VERSION + 1).to_le_bytes());
+        let mut tuple = good.clone();
+        tuple[37] = 16;
+        let mut trailing = good.clone();
+        trailing.push(0);
+        let truncated = &good[..good.len() - 1];
+        let mut long = good[..36].to_vec();
+        long.push(8);
+        long.extend(0..8);
+        // A thousand tuples of the longest kind, without their 64 GiB of
+        // weights.
+        let mut huge = good[..32].to_vec();
+        huge.extend(1000u32.to_le_bytes());
+        for _ in 0..1000 {
+            huge.push(MAX_TUPLE_LENGTH as u8);
+            huge.extend(0..MAX_TUPLE_LENGTH as u8);
+        }
+
+        let error = |bytes: &[u8]| Training::read_from(&mut &bytes[..]).unwrap_err();
+        assert!(matches!(error(&magic), WeightsError::NotWeights));
+        assert!(matches!(error(&version), WeightsError::Version(v) if v == WEIGHTS_VERSION + 1));
+        assert!(matches!(error(&tuple), WeightsError::Tuple(_)));
+        assert!(matches!(error(&trailing), WeightsError::TrailingData));
+        assert!(matches!(error(truncated), WeightsError::Io(_)));
+        assert!(matches!(error(&long), WeightsError::Tuple(t) if t.len() == 8));
+        assert!(matches!(error(&huge), WeightsError::Io(_)));
+    }
+}
@@ -0,0 +1,198 @@
+//! Trains an n-tuple network by self-play and compares it with the
+//! hand-written expectimax heuristics.
+//!
+//! ```text
+//! ntuple train [--weights PATH] [--games N] [--seed N] [--learning-rate X] [--checkpoint-every N]
+//! ntuple evaluate [--weights PATH] [--games N] [--seed N] [--depth N]
+//! ```
+//!
+//! Training saves a checkpoint to the weights file every few games. Running
+//! the same command again picks up from the last checkpoint and carries on
+//! until `--games` games have been played in total. The game only uses
+//! weights copied to its own `ntuple.bin`, and only once the n-tuple solver
+//! is picked in its menu.
+
+#![allow(clippy::needless_return)]
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Instant;
+
+use two_thousand_forty_eight_engine::ai::{Agent, Expectimax, NTupleNetwork, Training};
+use two_thousand_forty_eight_engine::Grid;
+
+const USAGE: &str = "\
+usage: ntuple train [--weights PATH] [--games N] [--seed N] [--learning-rate X] [--checkpoint-every N]
+       ntuple evaluate [--weights PATH] [--games N] [--seed N] [--depth N]";
+
+/// Kept apart from the `ntuple.bin` the game loads, so a run in progress
+/// never reaches the game. Copy the file over once it plays well enough.
+const DEFAULT_WEIGHTS: &str = "ntuple-training.bin";
+
+struct Options {
+    weights: PathBuf,
+    games: Option<u64>,
+    seed: Option<u64>,
+    learning_rate: Option<f32>,
+    checkpoint_every: u64,
+    depth: u32,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.split_first() {
+        Some((command, rest)) if command == "train" => parse(rest).and_then(train),
+        Some((command, rest)) if command == "evaluate" => parse(rest).and_then(evaluate),
+        _ => Err(USAGE.to_string()),
+    };
+    if let Err(error) = result {
+        eprintln!("{error}");
+        return ExitCode::FAILURE;
+    }
+    return ExitCode::SUCCESS;
+}
+
+fn parse(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        weights: PathBuf::from(DEFAULT_WEIGHTS),
+        games: None,
+        seed: None,
+        learning_rate: None,
+        checkpoint_every: 1000,
+        depth: 2,
+    };
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{flag} needs a value\n{USAGE}"))?;
+        match flag.as_str() {
+            "--weights" => options.weights = PathBuf::from(value),
+            "--games" => options.games = Some(number(flag, value)?),
+            "--seed" => options.seed = Some(number(flag, value)?),
+            "--learning-rate" => options.learning_rate = Some(number(flag, value)?),
+            "--checkpoint-every" => options.checkpoint_every = number(flag, value)?,
+            "--depth" => options.depth = number(flag, value)?,
+            _ => return Err(format!("Unknown option {flag}\n{USAGE}")),
+        }
+    }
+    return Ok(options);
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    return value
+        .parse()
+        .map_err(|_| format!("{flag}: \"{value}\" is not a valid number"));
+}
+
+fn train(options: Options) -> Result<(), String> {
+    let path = &options.weights;
+    let mut training = if path.exists() {
+        let training =
+            Training::load(path).map_err(|error| format!("{}: {error}", path.display()))?;
+        if options.seed.is_some_and(|seed| seed != training.seed)
+            || options
+                .learning_rate
+                .is_some_and(|rate| rate != training.learning_rate)
+        {
+            return Err(format!(
+                "{} was trained with seed {} and learning rate {}. Move it away to start over.",
+                path.display(),
+                training.seed,
+                training.learning_rate
+            ));
+        }
+        println!("Resuming {} after {} games", path.display(), training.games);
+        training
+    } else {
+        Training::new(
+            NTupleNetwork::standard(),
+            options.seed.unwrap_or(0),
+            options.learning_rate.unwrap_or(0.0025),
+        )
+    };
+
+    let games = options.games.unwrap_or(100_000);
+    let every = options.checkpoint_every.max(1);
+    let mut window = Window::default();
+    while training.games < games {
+        let grid = training.play_game();
+        window.add(&grid);
+        if training.games % every == 0 || training.games == games {
+            training
+                .save(path)
+                .map_err(|error| format!("{}: {error}", path.display()))?;
+            println!("Games {:>8}: {}", training.games, window.summary());
+            window = Window::default();
+        }
+    }
+    return Ok(());
+}
+
+fn evaluate(options: Options) -> Result<(), String> {
+    let path = &options.weights;
+    let network =
+        NTupleNetwork::load(path).map_err(|error| format!("{}: {error}", path.display()))?;
+    let expectimax = Expectimax {
+        depth: options.depth,
+        ..Default::default()
+    };
+    let games = options.games.unwrap_or(20);
+    let seed = options.seed.unwrap_or(0);
+
+    println!("{games} games from seed {seed}");
+    let agents: [(String, &dyn Agent); 2] = [
+        ("n-tuple".to_string(), &network),
+        (format!("expectimax depth {}", options.depth), &expectimax),
+    ];
+    for (name, agent) in agents {
+        let start = Instant::now();
+        let mut window = Window::default();
+        let mut moves = 0;
+        for game in 0..games {
+            let mut grid = Grid::with_seed(4, 4, seed.wrapping_add(game));
+            grid.add_starting_boxes();
+            while let Some(direction) = agent.best_move(&grid) {
+                grid.update(direction);
+            }
+            moves += grid.moves();
+            window.add(&grid);
+        }
+        let per_move = start.elapsed().as_secs_f64() * 1000.0 / moves.max(1) as f64;
+        println!(
+            "{name:>20}: {}, {per_move:.3} ms per move",
+            window.summary()
+        );
+    }
+    return Ok(());
+}
+
+/// Results of a run of games.
+#[derive(Default)]
+struct Window {
+    games: u32,
+    total_score: u64,
+    wins: u32,
+    best_tile: u32,
+}
+
+impl Window {
+    fn add(&mut self, grid: &Grid) {
+        self.games += 1;
+        self.total_score += grid.score as u64;
+        if grid.max_tile() >= 2048 {
+            self.wins += 1;
+        }
+        self.best_tile = self.best_tile.max(grid.max_tile());
+    }
+
+    fn summary(&self) -> String {
+        let games = self.games.max(1);
+        return format!(
+            "average score {:>6}, 2048 in {:>5.1}%, best tile {}",
+            self.total_score / games as u64,
+            100.0 * self.wins as f64 / games as f64,
+            self.best_tile
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::sync::OnceLock;
+
+use crate::direction::*;
+use crate::grid::Tile;
+
+/// Largest tile a [`Bitboard`] can hold. Tiles are stored as 4-bit exponents,
+/// so 2^15 is the ceiling.
+pub const MAX_TILE: u32 = 1 << 15;
+
+const ROW_MASK: u64 = 0xFFFF;
+
+/// A 4x4 board packed into a `u64`.
+///
+/// Each cell is a 4-bit exponent (`0` for empty, `n` for a tile of `2^n`).
+/// Cell `(i, j)` lives in nibble `4 * i + j`, so row `i` occupies bits
+/// `16 * i..16 * (i + 1)` with column 0 in the lowest nibble.
+///
+/// Moves are resolved through lookup tables indexed by whole rows, which makes
+/// them cheap enough for game-tree search. Two `MAX_TILE` tiles never merge,
+/// since their sum does not fit in a nibble.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bitboard(pub u64);
+
+struct MoveTables {
+    left: Vec<u16>,
+    score: Vec<u32>,
+}
+
+fn tables() -> &'static MoveTables {
+    static TABLES: OnceLock<MoveTables> = OnceLock::new();
+    return TABLES.get_or_init(|| {
+        let mut left = vec![0u16; 1 << 16];
+        let mut score = vec![0u32; 1 << 16];
+        for row in 0..=u16::MAX {
+            let (moved, gained) = slide_row_left(row);
+            left[row as usize] = moved;
+            score[row as usize] = gained;
+        }
+        return MoveTables { left, score };
+    });
+}
+
+/// Reference implementation of a left move on a single packed row, mirroring
+/// the compress, merge, compress passes of `Grid`.
+fn slide_row_left(row: u16) -> (u16, u32) {
+    let mut cells = [0u16; 4];
+    let mut index = 0;
+    for j in 0..4 {
+        let cell = (row >> (4 * j)) & 0xF;
+        if cell != 0 {
+            cells[index] = cell;
+            index += 1;
+        }
+    }
+
+    let mut merged = [0u16; 4];
+    let mut index = 0;
+    let mut score = 0;
+    let mut j = 0;
+    while j < 4 {
+        if j + 1 < 4 && cells[j] != 0 && cells[j] < 15 && cells[j] == cells[j + 1] {
+            merged[index] = cells[j] + 1;
+            score += 1 << (cells[j] + 1);
+            j += 2;
+        } else {
+            merged[index] = cells[j];
+            j += 1;
+        }
+        index += 1;
+    }
+
+    let mut packed = 0u16;
+    for (j, cell) in merged.iter().enumerate() {
+        packed |= cell << (4 * j);
+    }
+    return (packed, score);
+}
+
+fn reverse_row(row: u16) -> u16 {
+    return (row >> 12) | ((row >> 4) & 0x00F0) | ((row << 4) & 0x0F00) | (row << 12);
+}
+
+impl Bitboard {
+    /// Packs a 4x4 matrix of tile values. Returns `None` if the matrix is not
+    /// 4x4 or holds a value that is not a power of two up to [`MAX_TILE`].
+    pub fn from_state(state: &[Vec<Tile>]) -> Option<Self> {
+        if state.len() != 4 || state.iter().any(|row| row.len() != 4) {
+            return None;
+        }
+        let mut board = 0u64;
+        for i in 0..4 {
+            for j in 0..4 {
+                let Ok(value) = u32::try_from(state[i][j]) else {
+                    return None;
+                };
+                if value == 0 {
+                    continue;
+                }
+                if value == 1 || !value.is_power_of_two() || value > MAX_TILE {
+                    return None;
+                }
+                board |= (value.trailing_zeros() as u64) << (4 * (4 * i + j));
+            }
+        }
+        return Some(Bitboard(board));
+    }
+
+    pub fn to_state(self) -> Vec<Vec<Tile>> {
+        let mut state = vec![vec![0; 4]; 4];
+        for i in 0..4 {
+            for j in 0..4 {
+                state[i][j] = self.get(i, j) as Tile;
+            }
+        }
+        return state;
+    }
+
+    /// Returns the tile value at `(i, j)`, or `0` for an empty cell.
+    pub fn get(self, i: usize, j: usize) -> u32 {
+        let exponent = self.exponent(i, j);
+        return if exponent == 0 { 0 } else { 1 << exponent };
+    }
+
+    /// Returns the exponent at `(i, j)`: `0` for an empty cell, `n` for a tile
+    /// of `2^n`.
+    pub fn exponent(self, i: usize, j: usize) -> u32 {
+        return ((self.0 >> (4 * (4 * i + j))) & 0xF) as u32;
+    }
+
+    /// Places a tile of `value` at `(i, j)`. `value` must be `0` or a power of
+    /// two up to [`MAX_TILE`].
+    pub fn set(self, i: usize, j: usize, value: u32) -> Self {
+        let shift = 4 * (4 * i + j);
+        let exponent = if value == 0 {
+            0
+        } else {
+            value.trailing_zeros() as u64
+        };
+        return Bitboard((self.0 & !(0xF << shift)) | (exponent << shift));
+    }
+
+    pub fn empty_cells(self) -> u32 {
+        let mut count = 0;
+        for k in 0..16 {
+            if (self.0 >> (4 * k)) & 0xF == 0 {
+                count += 1;
+            }
+        }
+        return count;
+    }
+
+    pub fn max_tile(self) -> u32 {
+        let mut max = 0;
+        for k in 0..16 {
+            max = max.max((self.0 >> (4 * k)) & 0xF);
+        }
+        return if max == 0 { 0 } else { 1 << max };
+    }
+
+    pub fn transpose(self) -> Self {
+        let x = self.0;
+        let a1 = x & 0xF0F0_0F0F_F0F0_0F0F;
+        let a2 = x & 0x0000_F0F0_0000_F0F0;
+        let a3 = x & 0x0F0F_0000_0F0F_0000;
+        let a = a1 | (a2 << 12) | (a3 >> 12);
+        let b1 = a & 0xFF00_FF00_00FF_00FF;
+        let b2 = a & 0x00FF_00FF_0000_0000;
+        let b3 = a & 0x0000_0000_FF00_FF00;
+        return Bitboard(b1 | (b2 >> 24) | (b3 << 24));
+    }
+
+    fn move_rows(self, reversed: bool) -> (Self, u32) {
+        let tables = tables();
+        let mut board = 0u64;
+        let mut score = 0;
+        for i in 0..4 {
+            let mut row = ((self.0 >> (16 * i)) & ROW_MASK) as u16;
+            if reversed {
+                row = reverse_row(row);
+            }
+            let mut moved = tables.left[row as usize];
+            score += tables.score[row as usize];
+            if reversed {
+                moved = reverse_row(moved);
+            }
+            board |= (moved as u64) << (16 * i);
+        }
+        return (Bitboard(board), score);
+    }
+
+    /// Slides every tile in `direction` and returns the new board together with
+    /// the score gained from merges. No tile is spawned.
+    pub fn move_in_dir(self, direction: MoveDirection) -> (Self, u32) {
+        match direction {
+            MoveDirection::Left => {
+                return self.move_rows(false);
+            }
+            MoveDirection::Right => {
+                return self.move_rows(true);
+            }
+            MoveDirection::Up => {
+                let (board, score) = self.transpose().move_rows(false);
+                return (board.transpose(), score);
+            }
+            MoveDirection::Down => {
+                let (board, score) = self.transpose().move_rows(true);
+                return (board.transpose(), score);
+            }
+        }
+    }
+
+    pub fn is_deadlocked(self) -> bool {
+        return DIRECTIONS
+            .iter()
+            .all(|direction| self.move_in_dir(*direction).0 == self);
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt::Debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoveDirection {
+    Up,
+    Left,
+    Down,
+    Right,
+}
+
+pub const DIRECTIONS: [MoveDirection; 4] = [
+    MoveDirection::Up,
+    MoveDirection::Left,
+    MoveDirection::Down,
+    MoveDirection::Right,
+];
+
+/// Directions on a hex board of flat-topped cells, clockwise from north.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HexDirection {
+    North,
+    NorthEast,
+    SouthEast,
+    South,
+    SouthWest,
+    NorthWest,
+}
+
+pub const HEX_DIRECTIONS: [HexDirection; 6] = [
+    HexDirection::North,
+    HexDirection::NorthEast,
+    HexDirection::SouthEast,
+    HexDirection::South,
+    HexDirection::SouthWest,
+    HexDirection::NorthWest,
+];
+
+impl HexDirection {
+    /// Axial `(q, r)` offset of a single step.
+    pub fn axial(self) -> (isize, isize) {
+        match self {
+            HexDirection::North => (0, -1),
+            HexDirection::NorthEast => (1, -1),
+            HexDirection::SouthEast => (1, 0),
+            HexDirection::South => (0, 1),
+            HexDirection::SouthWest => (-1, 1),
+            HexDirection::NorthWest => (-1, 0),
+        }
+    }
+}
+
+/// Directions on a cube, where `Forward` and `Back` move between layers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CubeDirection {
+    Up,
+    Left,
+    Down,
+    Right,
+    Forward,
+    Back,
+}
+
+pub const CUBE_DIRECTIONS: [CubeDirection; 6] = [
+    CubeDirection::Up,
+    CubeDirection::Left,
+    CubeDirection::Down,
+    CubeDirection::Right,
+    CubeDirection::Forward,
+    CubeDirection::Back,
+];
+
+/// A direction tiles can slide in on some board shape.
+pub trait Direction: Debug + Clone + Copy + PartialEq + Eq {
+    /// Row, column and layer offset of a single step. Only cubes have more
+    /// than one layer.
+    fn step(self) -> (isize, isize, isize);
+
+    /// The square-board move with the same step, if any. On flat boards those
+    /// moves take the faster row and column passes.
+    fn as_move_direction(self) -> Option<MoveDirection>;
+}
+
+impl Direction for MoveDirection {
+    fn step(self) -> (isize, isize, isize) {
+        match self {
+            MoveDirection::Up => (-1, 0, 0),
+            MoveDirection::Left => (0, -1, 0),
+            MoveDirection::Down => (1, 0, 0),
+            MoveDirection::Right => (0, 1, 0),
+        }
+    }
+
+    fn as_move_direction(self) -> Option<MoveDirection> {
+        return Some(self);
+    }
+}
+
+/// Hex cells are stored with row `r` and column `q`, so four of the six
+/// directions run along rows and columns and the other two along diagonals.
+impl Direction for HexDirection {
+    fn step(self) -> (isize, isize, isize) {
+        let (q, r) = self.axial();
+        return (r, q, 0);
+    }
+
+    fn as_move_direction(self) -> Option<MoveDirection> {
+        match self {
+            HexDirection::North => Some(MoveDirection::Up),
+            HexDirection::SouthEast => Some(MoveDirection::Right),
+            HexDirection::South => Some(MoveDirection::Down),
+            HexDirection::NorthWest => Some(MoveDirection::Left),
+            HexDirection::NorthEast | HexDirection::SouthWest => None,
+        }
+    }
+}
+
+/// Layer 0 is the front of the cube, so `Forward` slides tiles towards it.
+impl Direction for CubeDirection {
+    fn step(self) -> (isize, isize, isize) {
+        match self {
+            CubeDirection::Up => (-1, 0, 0),
+            CubeDirection::Left => (0, -1, 0),
+            CubeDirection::Down => (1, 0, 0),
+            CubeDirection::Right => (0, 1, 0),
+            CubeDirection::Forward => (0, 0, -1),
+            CubeDirection::Back => (0, 0, 1),
+        }
+    }
+
+    /// Layers sit side by side in the matrix, so even `Left` and `Right` would
+    /// cross between layers on the row and column passes.
+    fn as_move_direction(self) -> Option<MoveDirection> {
+        return None;
+    }
+}
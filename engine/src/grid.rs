@@ -0,0 +1,1283 @@
+#[cfg(feature = "bevy")]
+use bevy_ecs::{reflect::ReflectResource, system::Resource};
+#[cfg(feature = "bevy")]
+use bevy_reflect::Reflect;
+
+use rand::Rng;
+
+use crate::bitboard::{Bitboard, MAX_TILE};
+use crate::direction::*;
+use crate::history::{History, Snapshot};
+use crate::outcome::*;
+use crate::rng::GridRng;
+use crate::rules::{ClassicRule, MergeRule};
+use crate::shape::Shape;
+use crate::spawn::SpawnRules;
+
+/// Value held by a cell: `0` when empty, otherwise a tile, [`WALL`] or
+/// [`HOLE`]. Tiles are signed so that rules can have negative tiles.
+pub type Tile = i32;
+
+/// Cell value of an immovable wall. Tiles stop against walls and never merge
+/// with them.
+pub const WALL: Tile = Tile::MAX;
+
+/// Cell value of a matrix cell that is not part of the board, such as the
+/// corners of a hex board. Tiles treat it like a wall, but it is never drawn.
+pub const HOLE: Tile = Tile::MAX - 1;
+
+fn is_blocked(value: Tile) -> bool {
+    return value == WALL || value == HOLE;
+}
+
+#[derive(Default)]
+#[cfg_attr(feature = "bevy", derive(Resource, Reflect))]
+#[cfg_attr(feature = "bevy", reflect(Resource))]
+pub struct Grid {
+    shape: Shape,
+    rows: usize,
+    columns: usize,
+    pub state: Vec<Vec<Tile>>,
+    pub score: u32,
+    /// Moves played this game. Moves that change nothing are not counted.
+    moves: u32,
+    pub deadlocked: bool,
+    pub spawn_rules: SpawnRules,
+    #[cfg_attr(feature = "bevy", reflect(ignore))]
+    pub merge_rule: Box<dyn MergeRule>,
+    /// Maximum undos per game. `None` is unlimited and `Some(0)` disables undo.
+    pub undo_limit: Option<u32>,
+    undos_used: u32,
+    seed: u64,
+    #[cfg_attr(feature = "bevy", reflect(ignore))]
+    rng: GridRng,
+    #[cfg_attr(feature = "bevy", reflect(ignore))]
+    history: History,
+}
+
+impl Grid {
+    /// Creates an empty `rows` x `columns` grid seeded from system entropy.
+    pub fn new(rows: usize, columns: usize) -> Self {
+        return Grid::with_seed(rows, columns, rand::random());
+    }
+
+    /// Creates an empty grid whose spawns are fully determined by `seed`.
+    pub fn with_seed(rows: usize, columns: usize, seed: u64) -> Self {
+        return Grid::with_shape(Shape::Rectangle { rows, columns }, seed);
+    }
+
+    /// Creates an empty hex board with `radius` cells from the centre to each
+    /// side, seeded from system entropy.
+    pub fn new_hex(radius: usize) -> Self {
+        return Grid::with_shape(Shape::Hex { radius }, rand::random());
+    }
+
+    /// Creates an empty `size` x `size` x `size` cube seeded from system
+    /// entropy.
+    pub fn new_cube(size: usize) -> Self {
+        return Grid::with_shape(Shape::Cube { size }, rand::random());
+    }
+
+    pub fn with_shape(shape: Shape, seed: u64) -> Self {
+        let (rows, columns) = shape.dimensions();
+        return Grid {
+            shape,
+            rows,
+            columns,
+            state: empty_state(shape),
+            score: 0,
+            moves: 0,
+            deadlocked: false,
+            spawn_rules: SpawnRules::classic(),
+            merge_rule: Box::new(ClassicRule),
+            undo_limit: None,
+            undos_used: 0,
+            seed,
+            rng: GridRng::from_seed(seed),
+            history: History::default(),
+        };
+    }
+
+    /// A copy of the grid for trying out moves, such as in rollouts. Its
+    /// spawns follow `seed` instead of the original's and it keeps no undo
+    /// history.
+    pub fn fork(&self, seed: u64) -> Grid {
+        return Grid {
+            shape: self.shape,
+            rows: self.rows,
+            columns: self.columns,
+            state: self.state.clone(),
+            score: self.score,
+            moves: self.moves,
+            deadlocked: self.deadlocked,
+            spawn_rules: self.spawn_rules.clone(),
+            merge_rule: self.merge_rule.clone(),
+            undo_limit: self.undo_limit,
+            undos_used: self.undos_used,
+            seed,
+            rng: GridRng::from_seed(seed),
+            history: History::new(0),
+        };
+    }
+
+    pub fn shape(&self) -> Shape {
+        return self.shape;
+    }
+
+    /// Switches to another board layout and clears the board and history.
+    /// Call `reset` afterwards to start a game on it.
+    pub fn set_shape(&mut self, shape: Shape) {
+        (self.rows, self.columns) = shape.dimensions();
+        self.shape = shape;
+        self.state = empty_state(shape);
+        self.score = 0;
+        self.moves = 0;
+        self.deadlocked = false;
+        self.history.clear();
+        self.undos_used = 0;
+    }
+
+    pub fn rows(&self) -> usize {
+        return self.rows;
+    }
+
+    pub fn columns(&self) -> usize {
+        return self.columns;
+    }
+
+    /// Largest positive tile on the board, or `0` if there is none.
+    pub fn max_tile(&self) -> u32 {
+        return self
+            .state
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|value| !is_blocked(*value))
+            .max()
+            .map_or(0, |value| value.max(0) as u32);
+    }
+
+    pub fn moves(&self) -> u32 {
+        return self.moves;
+    }
+
+    pub fn seed(&self) -> u64 {
+        return self.seed;
+    }
+
+    /// Starts a new game with a fresh seed drawn from system entropy.
+    pub fn reset(&mut self) -> &mut Self {
+        return self.reset_with_seed(rand::random());
+    }
+
+    pub fn reset_with_seed(&mut self, seed: u64) -> &mut Self {
+        self.restart(empty_state(self.shape), seed);
+        self.add_starting_boxes();
+        return self;
+    }
+
+    /// Starts a new game from `state` instead of spawning the starting tiles.
+    /// `state` must have the grid's dimensions.
+    pub fn reset_with_state(&mut self, state: Vec<Vec<Tile>>) -> &mut Self {
+        assert_eq!(
+            dimensions(&state),
+            (self.rows, self.columns),
+            "state does not match the grid's dimensions"
+        );
+        self.restart(state, rand::random());
+        self.deadlocked = self.is_deadlocked();
+        return self;
+    }
+
+    fn restart(&mut self, state: Vec<Vec<Tile>>, seed: u64) {
+        self.state = state;
+        self.score = 0;
+        self.moves = 0;
+        self.deadlocked = false;
+        self.seed = seed;
+        self.rng = GridRng::from_seed(seed);
+        self.history.clear();
+        self.undos_used = 0;
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        return Snapshot {
+            state: self.state.clone(),
+            score: self.score,
+            moves: self.moves,
+            deadlocked: self.deadlocked,
+            rng: self.rng.clone(),
+        };
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.state = snapshot.state;
+        self.score = snapshot.score;
+        self.moves = snapshot.moves;
+        self.deadlocked = snapshot.deadlocked;
+        self.rng = snapshot.rng;
+    }
+
+    /// Undos left this game, or `None` if unlimited.
+    pub fn undos_remaining(&self) -> Option<u32> {
+        return self
+            .undo_limit
+            .map(|limit| limit.saturating_sub(self.undos_used));
+    }
+
+    pub fn can_undo(&self) -> bool {
+        return self.undos_remaining() != Some(0) && self.history.can_undo();
+    }
+
+    pub fn can_redo(&self) -> bool {
+        return self.history.can_redo();
+    }
+
+    /// Takes back the last move. Returns `false` if there is nothing to undo
+    /// or the undo limit has been reached.
+    pub fn undo(&mut self) -> bool {
+        if !self.can_undo() {
+            return false;
+        }
+        let Some(previous) = self.history.undo(self.snapshot()) else {
+            return false;
+        };
+        self.restore(previous);
+        self.undos_used += 1;
+        return true;
+    }
+
+    /// Replays the last undone move. Returns `false` if there is nothing to
+    /// redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(next) = self.history.redo(self.snapshot()) else {
+            return false;
+        };
+        self.restore(next);
+        return true;
+    }
+
+    /// Places the starting walls, then spawns the number of starting tiles
+    /// given by the spawn rules.
+    pub fn add_starting_boxes(&mut self) -> Vec<TileSpawn> {
+        for _i in 0..self.spawn_rules.starting_walls {
+            self.add_wall();
+        }
+        return self.add_boxes(self.spawn_rules.starting_tiles);
+    }
+
+    pub fn walls(&self) -> usize {
+        return self
+            .state
+            .iter()
+            .flatten()
+            .filter(|value| **value == WALL)
+            .count();
+    }
+
+    /// Turns a random empty cell into a wall and returns it, or `None` if the
+    /// board is full.
+    pub fn add_wall(&mut self) -> Option<Position> {
+        let cells = self.empty_cells();
+        if cells.is_empty() {
+            return None;
+        }
+        let position = cells[self.rng.gen_range(0..cells.len())];
+        self.state[position.i][position.j] = WALL;
+        return Some(position);
+    }
+
+    /// Rolls for a new wall after a move, respecting `wall_chance` and
+    /// `max_walls`.
+    fn spawn_wall(&mut self) -> Option<Position> {
+        if self.spawn_rules.wall_chance == 0
+            || self.walls() >= self.spawn_rules.max_walls as usize
+            || self.rng.gen_range(0..100) >= self.spawn_rules.wall_chance
+        {
+            return None;
+        }
+        return self.add_wall();
+    }
+
+    /// Spawns up to `quantity` tiles on random empty cells and returns them.
+    /// Stops early once the board is full.
+    pub fn add_boxes(&mut self, quantity: u8) -> Vec<TileSpawn> {
+        return self.spawn::<MoveDirection>(quantity, None);
+    }
+
+    /// Cells on the edge that tiles slid away from when moving in `direction`.
+    fn trailing_edge<D: Direction>(&self, direction: D) -> Vec<Position> {
+        return self
+            .lines(direction)
+            .iter()
+            .filter_map(|line| line.last().copied())
+            .collect();
+    }
+
+    fn empty_cells(&self) -> Vec<Position> {
+        let mut cells = vec![];
+        for i in 0..self.rows {
+            for j in 0..self.columns {
+                if self.state[i][j] == 0 {
+                    cells.push(Position { i, j });
+                }
+            }
+        }
+        return cells;
+    }
+
+    fn spawn<D: Direction>(&mut self, quantity: u8, edge: Option<D>) -> Vec<TileSpawn> {
+        let mut spawns = vec![];
+        for _i in 0..quantity {
+            let mut cells = match edge {
+                Some(direction) => self
+                    .trailing_edge(direction)
+                    .into_iter()
+                    .filter(|position| self.state[position.i][position.j] == 0)
+                    .collect(),
+                None => vec![],
+            };
+            if cells.is_empty() {
+                cells = self.empty_cells();
+            }
+            if cells.is_empty() {
+                break;
+            }
+
+            let position = cells[self.rng.gen_range(0..cells.len())];
+            let Some(value) = self.spawn_rules.pick_value(&mut self.rng) else {
+                break;
+            };
+            self.state[position.i][position.j] = value;
+            spawns.push(TileSpawn { position, value });
+        }
+        return spawns;
+    }
+
+    fn compare(&self, matrix: &[Vec<Tile>]) -> bool {
+        for i in 0..self.rows {
+            for j in 0..self.columns {
+                if self.state[i][j] != matrix[i][j] {
+                    return true;
+                }
+            }
+        }
+        return false;
+    }
+
+    /// A board is deadlocked when no direction changes it. This holds for any
+    /// merge rule, including ones that need more than two tiles to merge.
+    fn is_deadlocked(&self) -> bool {
+        match self.shape {
+            Shape::Rectangle { .. } | Shape::Torus { .. } => {
+                return self.legal_moves().next().is_none();
+            }
+            Shape::Hex { .. } => {
+                return self.legal_hex_moves().next().is_none();
+            }
+            Shape::Cube { .. } => {
+                return self.legal_cube_moves().next().is_none();
+            }
+        }
+    }
+
+    /// Turns a `rows` x `columns` matrix into a `columns` x `rows` one.
+    fn transpose(&self, matrix: &[Vec<Tile>]) -> Vec<Vec<Tile>> {
+        let (rows, columns) = dimensions(matrix);
+        let mut new_matrix = vec![vec![0; rows]; columns];
+        for i in 0..rows {
+            for j in 0..columns {
+                new_matrix[j][i] = matrix[i][j]
+            }
+        }
+        return new_matrix;
+    }
+
+    fn reverse(&self, matrix: &[Vec<Tile>]) -> Vec<Vec<Tile>> {
+        let (rows, columns) = dimensions(matrix);
+        let mut new_matrix = vec![vec![0; columns]; rows];
+        for i in 0..rows {
+            for j in 0..columns {
+                new_matrix[i][j] = matrix[i][columns - 1 - j];
+            }
+        }
+        return new_matrix;
+    }
+
+    /// Slides tiles to the left within each row. Walls and holes stay in
+    /// place and split the row into independent segments.
+    fn compress(&self, matrix: &[Vec<Tile>]) -> Vec<Vec<Tile>> {
+        let (rows, columns) = dimensions(matrix);
+        let mut new_matrix = vec![vec![0; columns]; rows];
+        for i in 0..rows {
+            let mut index: usize = 0;
+            for j in 0..columns {
+                if is_blocked(matrix[i][j]) {
+                    new_matrix[i][j] = matrix[i][j];
+                    index = j + 1;
+                } else if matrix[i][j] != 0 {
+                    new_matrix[i][index] = matrix[i][j];
+                    index += 1;
+                }
+            }
+        }
+        return new_matrix;
+    }
+
+    /// Value of the tile `tiles` merge into under the active rule, if they
+    /// merge at all. `tiles` must hold exactly one group.
+    fn combine(&self, tiles: &[Tile]) -> Option<Tile> {
+        if tiles.len() != self.merge_rule.group_size()
+            || tiles.iter().any(|value| *value == 0 || is_blocked(*value))
+        {
+            return None;
+        }
+        return self.merge_rule.merge(tiles);
+    }
+
+    /// Combines groups of neighbours in each row under the active merge rule
+    /// and returns the score gained. The merged tile takes the place of the
+    /// last tile in its group and is slid into place by the next compress.
+    fn merge(&self, matrix: &[Vec<Tile>]) -> (Vec<Vec<Tile>>, u32) {
+        let (rows, columns) = dimensions(matrix);
+        let group_size = self.merge_rule.group_size();
+        let mut new_matrix = vec![vec![0; columns]; rows];
+        let mut score = 0;
+        for i in 0..rows {
+            let mut j = 0;
+            while j < columns {
+                let group = matrix[i].get(j..j + group_size);
+                if let Some(merged) = group.and_then(|group| self.combine(group)) {
+                    new_matrix[i][j + group_size - 1] = merged;
+                    score += self.merge_rule.score(merged);
+                    j += group_size;
+                } else {
+                    new_matrix[i][j] = matrix[i][j];
+                    j += 1;
+                }
+            }
+        }
+        return (new_matrix, score);
+    }
+
+    fn move_left(&self, matrix: &[Vec<Tile>]) -> (Vec<Vec<Tile>>, u32) {
+        let (merged, score) = self.merge(&self.compress(matrix));
+        return (self.compress(&merged), score);
+    }
+
+    fn slide(&self, direction: MoveDirection) -> (Vec<Vec<Tile>>, u32) {
+        match direction {
+            MoveDirection::Left => {
+                return self.move_left(&self.state);
+            }
+            MoveDirection::Right => {
+                let (moved, score) = self.move_left(&self.reverse(&self.state));
+                return (self.reverse(&moved), score);
+            }
+            MoveDirection::Up => {
+                let (moved, score) = self.move_left(&self.transpose(&self.state));
+                return (self.transpose(&moved), score);
+            }
+            MoveDirection::Down => {
+                let (moved, score) = self.move_left(&self.reverse(&self.transpose(&self.state)));
+                return (self.transpose(&self.reverse(&moved)), score);
+            }
+        }
+    }
+
+    /// Packs the state into a [`Bitboard`] when it is 4x4 and no move on it
+    /// can produce a tile larger than the bitboard can hold.
+    fn bitboard(&self) -> Option<Bitboard> {
+        if self.rows != 4 || self.columns != 4 || !self.merge_rule.is_classic() {
+            return None;
+        }
+        return Bitboard::from_state(&self.state).filter(|board| board.max_tile() < MAX_TILE);
+    }
+
+    /// Returns the board that moving in `direction` would produce, or `None`
+    /// if the move would not change anything. The grid is left untouched and
+    /// no tile is spawned.
+    pub fn preview<D: Direction>(&self, direction: D) -> Option<Preview<D>> {
+        // Only flat boards can take the row and column passes. Wrapping rows
+        // have no ends to slide against, and a cube's layers sit side by side
+        // in the matrix, so its rows would run from one layer into the next.
+        let flat = matches!(self.shape, Shape::Rectangle { .. } | Shape::Hex { .. });
+        let square = direction.as_move_direction().filter(|_| flat);
+        let Some(square) = square else {
+            let outcome = self.trace(direction);
+            if !outcome.has_changed() {
+                return None;
+            }
+            return Some(Preview {
+                direction,
+                state: self.apply(&outcome),
+                score_delta: outcome.score_delta,
+            });
+        };
+
+        if let Some(board) = self.bitboard() {
+            let (moved, score_delta) = board.move_in_dir(square);
+            if moved == board {
+                return None;
+            }
+            return Some(Preview {
+                direction,
+                state: moved.to_state(),
+                score_delta,
+            });
+        }
+
+        let (state, score_delta) = self.slide(square);
+        if !self.compare(&state) {
+            return None;
+        }
+        return Some(Preview {
+            direction,
+            state,
+            score_delta,
+        });
+    }
+
+    /// Directions that would change the board, in [`DIRECTIONS`] order.
+    pub fn legal_moves(&self) -> impl Iterator<Item = MoveDirection> + '_ {
+        return DIRECTIONS
+            .into_iter()
+            .filter(|direction| self.preview(*direction).is_some());
+    }
+
+    /// Hex directions that would change the board, in [`HEX_DIRECTIONS`]
+    /// order.
+    pub fn legal_hex_moves(&self) -> impl Iterator<Item = HexDirection> + '_ {
+        return HEX_DIRECTIONS
+            .into_iter()
+            .filter(|direction| self.preview(*direction).is_some());
+    }
+
+    /// Cube directions that would change the board, in [`CUBE_DIRECTIONS`]
+    /// order.
+    pub fn legal_cube_moves(&self) -> impl Iterator<Item = CubeDirection> + '_ {
+        return CUBE_DIRECTIONS
+            .into_iter()
+            .filter(|direction| self.preview(*direction).is_some());
+    }
+
+    /// Slides the tiles in `direction` and adds any merges to the score,
+    /// without spawning a tile or updating the deadlock flag.
+    pub fn move_in_dir<D: Direction>(&mut self, direction: D) -> &mut Self {
+        if let Some(preview) = self.preview(direction) {
+            self.state = preview.state;
+            self.score += preview.score_delta;
+        }
+        return self;
+    }
+
+    /// Cells of every line along `direction`, each ordered from the edge the
+    /// tiles slide towards. Holes are left out.
+    fn lines<D: Direction>(&self, direction: D) -> Vec<Vec<Position>> {
+        let (di, dj, dk) = direction.step();
+        let mut lines = vec![];
+        for i in 0..self.rows {
+            for j in 0..self.columns {
+                let start = Position { i, j };
+                if self.shape.neighbour(start, (di, dj, dk)).is_some() {
+                    continue;
+                }
+                let mut line = vec![];
+                let mut next = Some(start);
+                while let Some(position) = next {
+                    if self.state[position.i][position.j] != HOLE {
+                        line.push(position);
+                    }
+                    next = self.shape.neighbour(position, (-di, -dj, -dk));
+                }
+                if !line.is_empty() {
+                    lines.push(line);
+                }
+            }
+        }
+        return lines;
+    }
+
+    /// Works out where every tile would go when moving in `direction`,
+    /// without touching the grid.
+    fn trace<D: Direction>(&self, direction: D) -> MoveOutcome<D> {
+        let mut outcome = MoveOutcome::new(direction);
+        for line in self.lines(direction) {
+            if self.shape.wraps() {
+                self.trace_ring(&line, &mut outcome);
+                continue;
+            }
+            for segment in line.split(|position| is_blocked(self.state[position.i][position.j])) {
+                self.trace_segment(segment, &mut outcome);
+            }
+        }
+        return outcome;
+    }
+
+    /// Tiles on `line` in order, skipping empty cells.
+    fn tiles(&self, line: &[Position]) -> Vec<(Position, Tile)> {
+        return line
+            .iter()
+            .map(|position| (*position, self.state[position.i][position.j]))
+            .filter(|(_, value)| *value != 0)
+            .collect();
+    }
+
+    /// Splits `tiles` into the groups that merge, starting from the edge the
+    /// move is towards. Tiles that do not merge form a group of their own.
+    fn group(&self, tiles: &[(Position, Tile)]) -> Vec<Group> {
+        let group_size = self.merge_rule.group_size();
+        let mut groups = vec![];
+        let mut index = 0;
+        while index < tiles.len() {
+            let group = tiles.get(index..index + group_size);
+            let merged = group.and_then(|group| {
+                let values: Vec<Tile> = group.iter().map(|(_, value)| *value).collect();
+                return self.combine(&values);
+            });
+            if let (Some(merged), Some(group)) = (merged, group) {
+                groups.push(Group {
+                    tiles: group.to_vec(),
+                    value: merged,
+                });
+                index += group_size;
+            } else {
+                groups.push(Group {
+                    tiles: vec![tiles[index]],
+                    value: tiles[index].1,
+                });
+                index += 1;
+            }
+        }
+        return groups;
+    }
+
+    /// Packs `groups` against the start of `line` and records the moves and
+    /// merges that takes. Tiles that annihilate take up no cell, so the next
+    /// group packs into the cell they met in.
+    fn place<D: Direction>(
+        &self,
+        line: &[Position],
+        groups: Vec<Group>,
+        outcome: &mut MoveOutcome<D>,
+    ) {
+        let mut target = 0;
+        for group in groups.into_iter() {
+            if group.tiles.len() > 1 {
+                outcome.merges.push(TileMerge {
+                    sources: group.tiles.iter().map(|(position, _)| *position).collect(),
+                    to: line[target],
+                    value: group.value,
+                });
+                outcome.score_delta += self.merge_rule.score(group.value);
+            } else if group.tiles[0].0 != line[target] {
+                outcome.moves.push(TileMove {
+                    from: group.tiles[0].0,
+                    to: line[target],
+                    value: group.value,
+                });
+            }
+            if group.value != 0 {
+                target += 1;
+            }
+        }
+    }
+
+    /// Traces one run of cells between walls or board edges.
+    fn trace_segment<D: Direction>(&self, line: &[Position], outcome: &mut MoveOutcome<D>) {
+        let groups = self.group(&self.tiles(line));
+        self.place(line, groups, outcome);
+    }
+
+    /// Traces a line whose ends join up.
+    ///
+    /// A wall cuts the ring open, so the line is traced from the cell after
+    /// the wall, wrapping round the edge. Without walls, tiles merge as usual
+    /// and then the tile nearest the edge may wrap round and merge with the
+    /// last ones. Tiles still pack against the seam between the last and first
+    /// cell, so a move is a no-op exactly when no tile moves or merges, just
+    /// as on a flat board.
+    fn trace_ring<D: Direction>(&self, line: &[Position], outcome: &mut MoveOutcome<D>) {
+        if let Some(wall) = line
+            .iter()
+            .position(|position| is_blocked(self.state[position.i][position.j]))
+        {
+            let ring: Vec<Position> = line[wall + 1..]
+                .iter()
+                .chain(&line[..wall])
+                .copied()
+                .collect();
+            for segment in ring.split(|position| is_blocked(self.state[position.i][position.j])) {
+                self.trace_segment(segment, outcome);
+            }
+            return;
+        }
+
+        let tiles = self.tiles(line);
+        let mut groups = self.group(&tiles);
+
+        let group_size = self.merge_rule.group_size();
+        let singles = groups
+            .iter()
+            .rev()
+            .take_while(|group| group.tiles.len() == 1)
+            .count();
+        if groups.len() >= group_size && groups[0].tiles.len() == 1 && singles >= group_size - 1 {
+            let mut seam: Vec<(Position, Tile)> = groups[groups.len() + 1 - group_size..]
+                .iter()
+                .map(|group| group.tiles[0])
+                .collect();
+            seam.push(groups[0].tiles[0]);
+            let values: Vec<Tile> = seam.iter().map(|(_, value)| *value).collect();
+            if let Some(merged) = self.combine(&values) {
+                groups.truncate(groups.len() + 1 - group_size);
+                groups.remove(0);
+                groups.push(Group {
+                    tiles: seam,
+                    value: merged,
+                });
+            }
+        }
+
+        self.place(line, groups, outcome);
+    }
+
+    /// The board after every move and merge in `outcome`.
+    fn apply<D: Direction>(&self, outcome: &MoveOutcome<D>) -> Vec<Vec<Tile>> {
+        let mut state = self.state.clone();
+        for tile in outcome.moves.iter() {
+            state[tile.from.i][tile.from.j] = 0;
+        }
+        for merge in outcome.merges.iter() {
+            for source in merge.sources.iter() {
+                state[source.i][source.j] = 0;
+            }
+        }
+        for tile in outcome.moves.iter() {
+            state[tile.to.i][tile.to.j] = tile.value;
+        }
+        for merge in outcome.merges.iter().filter(|merge| merge.value != 0) {
+            state[merge.to.i][merge.to.j] = merge.value;
+        }
+        return state;
+    }
+
+    /// Plays a move. If any tile slides or merges, the move is counted, a new
+    /// tile is spawned and the deadlock flag is refreshed.
+    pub fn update<D: Direction>(&mut self, direction: D) -> MoveOutcome<D> {
+        let Some(preview) = self.preview(direction) else {
+            return MoveOutcome::new(direction);
+        };
+
+        let mut outcome = self.trace(direction);
+        self.history.record(self.snapshot());
+        self.state = preview.state;
+        self.score += preview.score_delta;
+        self.moves += 1;
+        let edge = self.spawn_rules.edge_only.then_some(direction);
+        outcome.spawns = self.spawn(self.spawn_rules.tiles_per_move, edge);
+        outcome.walls.extend(self.spawn_wall());
+        self.deadlocked = self.is_deadlocked();
+
+        return outcome;
+    }
+}
+
+/// Tiles that end up in the same cell: one tile that slides, or several that
+/// merge into `value`.
+struct Group {
+    tiles: Vec<(Position, Tile)>,
+    value: Tile,
+}
+
+/// An empty board of `shape`, with every cell outside it marked as a hole.
+fn empty_state(shape: Shape) -> Vec<Vec<Tile>> {
+    let (rows, columns) = shape.dimensions();
+    let mut state = vec![vec![0; columns]; rows];
+    for i in 0..rows {
+        for j in 0..columns {
+            if !shape.contains(Position { i, j }) {
+                state[i][j] = HOLE;
+            }
+        }
+    }
+    return state;
+}
+
+fn dimensions(matrix: &[Vec<Tile>]) -> (usize, usize) {
+    return (matrix.len(), matrix.first().map_or(0, Vec::len));
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::history::DEFAULT_HISTORY_CAPACITY;
+    use crate::rules::{NegativeRule, TriplesRule};
+
+    /// A cube with random small tiles on about half its cells.
+    fn random_cube(rng: &mut GridRng) -> Grid {
+        let mut grid = Grid::with_shape(Shape::Cube { size: 4 }, 0);
+        let mut state = grid.state.clone();
+        for row in state.iter_mut() {
+            for value in row.iter_mut() {
+                if rng.gen_bool(0.5) {
+                    *value = 1 << rng.gen_range(1..=3);
+                }
+            }
+        }
+        grid.reset_with_state(state);
+        return grid;
+    }
+
+    /// A hex board with random small tiles on about half its cells.
+    fn random_hex(rng: &mut GridRng) -> Grid {
+        let mut grid = Grid::with_shape(Shape::Hex { radius: 2 }, 0);
+        let mut state = grid.state.clone();
+        for value in state.iter_mut().flatten().filter(|value| **value != HOLE) {
+            if rng.gen_bool(0.5) {
+                *value = 1 << rng.gen_range(1..=3);
+            }
+        }
+        grid.reset_with_state(state);
+        return grid;
+    }
+
+    fn random_square(rng: &mut GridRng, max_exponent: u32) -> Grid {
+        return random_rectangle(rng, 4, 4, max_exponent);
+    }
+
+    /// A grid with tiles from `2^1` to `2^max_exponent` on about three
+    /// quarters of its cells.
+    fn random_rectangle(rng: &mut GridRng, rows: usize, columns: usize, max_exponent: u32) -> Grid {
+        let mut state = vec![vec![0; columns]; rows];
+        for row in state.iter_mut() {
+            for value in row.iter_mut() {
+                if rng.gen_bool(0.75) {
+                    *value = 1 << rng.gen_range(1..=max_exponent);
+                }
+            }
+        }
+        let mut grid = Grid::with_seed(rows, columns, 0);
+        grid.reset_with_state(state);
+        return grid;
+    }
+
+    /// Plays a fixed pattern of moves, trying each direction in turn, and
+    /// returns every board on the way.
+    fn replay(seed: u64) -> Vec<Vec<Vec<Tile>>> {
+        let mut grid = Grid::with_seed(4, 4, seed);
+        grid.reset_with_seed(seed);
+        let mut boards = vec![grid.state.clone()];
+        for turn in 0..300 {
+            let Some(direction) = (0..4)
+                .map(|k| DIRECTIONS[(turn + k) % 4])
+                .find(|direction| grid.preview(*direction).is_some())
+            else {
+                break;
+            };
+            grid.update(direction);
+            boards.push(grid.state.clone());
+        }
+        return boards;
+    }
+
+    /// A 4x4 game from `seed` with up to `moves` moves played, trying each
+    /// direction in turn.
+    fn played(seed: u64, moves: usize) -> Grid {
+        let mut grid = Grid::with_seed(4, 4, seed);
+        grid.reset_with_seed(seed);
+        for turn in 0..moves {
+            let Some(direction) = (0..4)
+                .map(|k| DIRECTIONS[(turn + k) % 4])
+                .find(|direction| grid.preview(*direction).is_some())
+            else {
+                break;
+            };
+            grid.update(direction);
+        }
+        return grid;
+    }
+
+    #[test]
+    fn undo_then_redo_puts_everything_back() {
+        for seed in 0..10 {
+            let mut grid = played(seed, 20);
+            let state = grid.state.clone();
+            let (score, moves) = (grid.score, grid.moves());
+            assert!(grid.undo());
+            assert_ne!(grid.state, state);
+            assert!(grid.redo());
+            assert!(!grid.redo());
+            assert_eq!(grid.state, state);
+            assert_eq!((grid.score, grid.moves()), (score, moves));
+
+            // The RNG came back too, so play carries on as if nothing happened.
+            let mut straight = played(seed, 20);
+            let direction = grid.legal_moves().next().unwrap();
+            assert_eq!(grid.update(direction), straight.update(direction));
+            assert_eq!(grid.state, straight.state);
+        }
+    }
+
+    #[test]
+    fn replaying_an_undone_move_spawns_the_same_tile() {
+        for seed in 0..10 {
+            let mut grid = played(seed, 20);
+            let direction = grid.legal_moves().next().unwrap();
+            let first = grid.update(direction);
+            let state = grid.state.clone();
+            assert!(grid.undo());
+            assert_eq!(grid.update(direction).spawns, first.spawns);
+            assert_eq!(grid.state, state);
+            // Playing a move drops what could be redone.
+            assert!(!grid.can_redo());
+        }
+    }
+
+    #[test]
+    fn undo_limits_count_every_undo() {
+        let mut grid = played(1, 10);
+        grid.undo_limit = Some(0);
+        assert!(!grid.can_undo());
+        assert!(!grid.undo());
+
+        grid.undo_limit = Some(2);
+        assert_eq!(grid.undos_remaining(), Some(2));
+        assert!(grid.undo());
+        assert!(grid.redo());
+        assert!(grid.undo());
+        assert_eq!(grid.undos_remaining(), Some(0));
+        assert!(!grid.undo());
+        assert!(grid.redo());
+
+        grid.reset_with_seed(2);
+        assert_eq!(grid.undos_remaining(), Some(2));
+        assert!(!grid.undo());
+        assert!(!grid.redo());
+    }
+
+    #[test]
+    fn undo_goes_back_as_far_as_the_history_holds() {
+        let mut grid = played(3, 100);
+        assert!(grid.moves() > DEFAULT_HISTORY_CAPACITY as u32);
+        let mut undos = 0;
+        while grid.undo() {
+            undos += 1;
+        }
+        assert_eq!(undos, DEFAULT_HISTORY_CAPACITY);
+        assert_eq!(grid.moves(), 100 - DEFAULT_HISTORY_CAPACITY as u32);
+    }
+
+    #[test]
+    fn same_seed_and_moves_give_the_same_boards() {
+        for seed in 0..10 {
+            assert_eq!(replay(seed), replay(seed));
+        }
+        assert_ne!(replay(1), replay(2));
+    }
+
+    #[test]
+    fn looking_for_moves_leaves_the_grid_alone() {
+        let mut rng = GridRng::from_seed(5);
+        for round in 0..500 {
+            // 4x4 boards take the bitboard path and 5x5 ones the slide path.
+            let size = if round % 2 == 0 { 4 } else { 5 };
+            let mut grid = random_rectangle(&mut rng, size, size, 4);
+            grid.score = 100;
+            let state = grid.state.clone();
+
+            for direction in DIRECTIONS {
+                grid.preview(direction);
+            }
+            grid.legal_moves().count();
+            grid.is_deadlocked();
+            assert_eq!(grid.state, state);
+            assert_eq!(grid.score, 100);
+
+            for direction in grid.legal_moves().collect::<Vec<_>>() {
+                let preview = grid.preview(direction).unwrap();
+                let mut played = grid.fork(0);
+                played.update(direction);
+                assert_eq!(played.score, 100 + preview.score_delta);
+            }
+        }
+    }
+
+    #[test]
+    fn a_game_scores_exactly_its_merges() {
+        for seed in 0..20 {
+            let mut grid = Grid::with_seed(4, 4, seed);
+            grid.reset_with_seed(seed);
+            let mut total = 0;
+            loop {
+                let Some(direction) = grid.legal_moves().next() else {
+                    break;
+                };
+                total += grid.preview(direction).unwrap().score_delta;
+                grid.update(direction);
+                assert_eq!(grid.score, total);
+            }
+            assert!(grid.deadlocked);
+        }
+    }
+
+    #[test]
+    fn bitboard_moves_match_the_slide_path() {
+        let mut rng = GridRng::from_seed(3);
+        for round in 0..3000 {
+            // Small tiles merge often. Large ones reach 16384 pairs, which
+            // make the largest tile a bitboard can hold.
+            let max_exponent = if round % 2 == 0 { 3 } else { 14 };
+            let grid = random_square(&mut rng, max_exponent);
+            let board = Bitboard::from_state(&grid.state).unwrap();
+            for direction in DIRECTIONS {
+                let (moved, score) = board.move_in_dir(direction);
+                let (state, slide_score) = grid.slide(direction);
+                assert_eq!(moved.to_state(), state, "{direction:?} on {:?}", grid.state);
+                assert_eq!(score, slide_score, "{direction:?} on {:?}", grid.state);
+            }
+        }
+    }
+
+    #[test]
+    fn grids_holding_the_largest_bitboard_tile_use_the_slide_path() {
+        let mut rng = GridRng::from_seed(4);
+        for _ in 0..500 {
+            let mut grid = random_square(&mut rng, 15);
+            let mut state = grid.state.clone();
+            state[0][0] = MAX_TILE as Tile;
+            state[0][1] = MAX_TILE as Tile;
+            grid.reset_with_state(state);
+            assert!(grid.bitboard().is_none());
+
+            for direction in DIRECTIONS {
+                let (state, score_delta) = grid.slide(direction);
+                match grid.preview(direction) {
+                    Some(preview) => {
+                        assert_eq!(preview.state, state);
+                        assert_eq!(preview.score_delta, score_delta);
+                    }
+                    None => assert!(!grid.compare(&state)),
+                }
+            }
+            let left = grid.preview(MoveDirection::Left).unwrap();
+            assert_eq!(left.state[0][0], 2 * MAX_TILE as Tile);
+        }
+    }
+
+    /// A one row torus holding `row`.
+    fn ring(row: &[Tile], merge_rule: Box<dyn MergeRule>) -> Grid {
+        let columns = row.len();
+        let mut grid = Grid::with_shape(Shape::Torus { rows: 1, columns }, 0);
+        grid.merge_rule = merge_rule;
+        grid.reset_with_state(vec![row.to_vec()]);
+        return grid;
+    }
+
+    /// The row left by moving a one row torus holding `row`, with the score
+    /// gained, or `None` if nothing moves.
+    fn ring_move(
+        row: &[Tile],
+        merge_rule: Box<dyn MergeRule>,
+        direction: MoveDirection,
+    ) -> Option<(Vec<Tile>, u32)> {
+        let preview = ring(row, merge_rule).preview(direction)?;
+        return Some((preview.state[0].clone(), preview.score_delta));
+    }
+
+    #[test]
+    fn rings_merge_across_the_seam() {
+        let classic = || Box::new(ClassicRule) as Box<dyn MergeRule>;
+        let left = MoveDirection::Left;
+        let right = MoveDirection::Right;
+        assert_eq!(
+            ring_move(&[2, 4, 0, 2], classic(), left),
+            Some((vec![4, 4, 0, 0], 4))
+        );
+        assert_eq!(
+            ring_move(&[2, 4, 0, 2], classic(), right),
+            Some((vec![0, 0, 4, 4], 4))
+        );
+        // Tiles that merge before the seam do not merge across it again.
+        assert_eq!(
+            ring_move(&[2, 2, 0, 2], classic(), left),
+            Some((vec![4, 2, 0, 0], 4))
+        );
+        assert_eq!(
+            ring_move(&[3, 0, 9, 3, 3], Box::new(TriplesRule), left),
+            Some((vec![9, 9, 0, 0, 0], 9))
+        );
+    }
+
+    #[test]
+    fn walls_cut_rings_open() {
+        let classic = || Box::new(ClassicRule) as Box<dyn MergeRule>;
+        let left = MoveDirection::Left;
+        // The line runs from the cell after the wall round to the one before.
+        assert_eq!(
+            ring_move(&[2, WALL, 2, 2], classic(), left),
+            Some((vec![0, WALL, 4, 2], 4))
+        );
+        assert_eq!(
+            ring_move(&[0, 2, WALL, 2], classic(), left),
+            Some((vec![0, 0, WALL, 4], 4))
+        );
+        assert_eq!(ring_move(&[2, WALL, 4, 8], classic(), left), None);
+    }
+
+    #[test]
+    fn ring_moves_are_no_ops_exactly_when_nothing_moves_or_merges() {
+        let classic = || Box::new(ClassicRule) as Box<dyn MergeRule>;
+        let left = MoveDirection::Left;
+        assert_eq!(ring_move(&[2, 4, 2, 4], classic(), left), None);
+        assert_eq!(ring_move(&[2, 4, 8, 0], classic(), left), None);
+        assert_eq!(
+            ring_move(&[0, 2, 4, 8], classic(), left),
+            Some((vec![2, 4, 8, 0], 0))
+        );
+        assert_eq!(
+            ring_move(&[2, 4, 8, 2], classic(), left),
+            Some((vec![4, 8, 4, 0], 4))
+        );
+        assert_eq!(
+            ring_move(&[3, 3, 9, 0, 0], Box::new(TriplesRule), left),
+            None
+        );
+
+        let full = ring(&[2, 4, 2, 4], classic());
+        assert!(full.deadlocked);
+    }
+
+    /// The row left by moving a one row grid holding `row` to the left under
+    /// [`NegativeRule`], with the score gained. Checks that the slide path
+    /// and the traced outcome agree.
+    fn negative_move_left(row: &[Tile]) -> Option<(Vec<Tile>, u32)> {
+        let mut grid = Grid::with_seed(1, row.len(), 0);
+        grid.merge_rule = Box::new(NegativeRule);
+        grid.reset_with_state(vec![row.to_vec()]);
+        let outcome = grid.trace(MoveDirection::Left);
+        let preview = grid.preview(MoveDirection::Left);
+        assert_eq!(preview.is_some(), outcome.has_changed());
+        let preview = preview?;
+        assert_eq!(preview.state, grid.apply(&outcome));
+        assert_eq!(preview.score_delta, outcome.score_delta);
+        return Some((preview.state[0].clone(), preview.score_delta));
+    }
+
+    #[test]
+    fn opposite_tiles_annihilate() {
+        assert_eq!(
+            negative_move_left(&[2, -2, 2, 2]),
+            Some((vec![4, 0, 0, 0], 4))
+        );
+        assert_eq!(
+            negative_move_left(&[-2, -2, 0, 0]),
+            Some((vec![-4, 0, 0, 0], 0))
+        );
+        assert_eq!(negative_move_left(&[0, 2, 0, -2]), Some((vec![0; 4], 0)));
+        assert_eq!(
+            negative_move_left(&[4, -4, -2, 8]),
+            Some((vec![-2, 8, 0, 0], 0))
+        );
+        assert_eq!(negative_move_left(&[2, -4, 2, 0]), None);
+    }
+
+    #[test]
+    fn negative_slides_match_their_traced_outcome() {
+        let mut rng = GridRng::from_seed(19);
+        for _ in 0..1000 {
+            let mut grid = random_rectangle(&mut rng, 5, 5, 3);
+            grid.merge_rule = Box::new(NegativeRule);
+            let mut state = grid.state.clone();
+            for value in state.iter_mut().flatten() {
+                if rng.gen_bool(0.5) {
+                    *value = -*value;
+                }
+            }
+            grid.reset_with_state(state);
+            for direction in DIRECTIONS {
+                let outcome = grid.trace(direction);
+                match grid.preview(direction) {
+                    Some(preview) => {
+                        assert_eq!(preview.state, grid.apply(&outcome), "{direction:?}");
+                        assert_eq!(preview.score_delta, outcome.score_delta);
+                    }
+                    None => assert!(!outcome.has_changed(), "{direction:?}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hex_boards_hold_every_cell_within_the_radius() {
+        for radius in 0..5 {
+            let grid = Grid::with_shape(Shape::Hex { radius }, 0);
+            let cells = grid.state.iter().flatten().filter(|value| **value != HOLE);
+            assert_eq!(cells.count(), 3 * radius * (radius + 1) + 1);
+        }
+        let grid = Grid::with_shape(Shape::Hex { radius: 1 }, 0);
+        assert_eq!(grid.state, [[HOLE, 0, 0], [0, 0, 0], [0, 0, HOLE]]);
+    }
+
+    #[test]
+    fn hex_moves_match_their_traced_outcome() {
+        let mut rng = GridRng::from_seed(13);
+        let mut moved = [0; 6];
+        for _ in 0..300 {
+            let grid = random_hex(&mut rng);
+            for (k, direction) in HEX_DIRECTIONS.into_iter().enumerate() {
+                let outcome = grid.trace(direction);
+                match grid.preview(direction) {
+                    Some(preview) => {
+                        moved[k] += 1;
+                        assert_eq!(preview.state, grid.apply(&outcome), "{direction:?}");
+                        assert_eq!(preview.score_delta, outcome.score_delta);
+                    }
+                    None => assert!(!outcome.has_changed(), "{direction:?}"),
+                }
+            }
+        }
+        assert!(moved.iter().all(|count| *count > 0));
+    }
+
+    #[test]
+    fn square_moves_on_a_cube_stay_within_each_layer() {
+        let mut grid = Grid::with_shape(Shape::Cube { size: 4 }, 0);
+        let mut state = grid.state.clone();
+        state[0][3] = 2;
+        grid.reset_with_state(state);
+
+        assert!(grid.preview(MoveDirection::Right).is_none());
+        let preview = grid.preview(MoveDirection::Left).unwrap();
+        assert_eq!(preview.state[0][0], 2);
+        assert_eq!(
+            grid.legal_moves().collect::<Vec<_>>(),
+            [MoveDirection::Left, MoveDirection::Down]
+        );
+    }
+
+    #[test]
+    fn square_moves_on_a_cube_match_cube_moves() {
+        let pairs = [
+            (MoveDirection::Up, CubeDirection::Up),
+            (MoveDirection::Left, CubeDirection::Left),
+            (MoveDirection::Down, CubeDirection::Down),
+            (MoveDirection::Right, CubeDirection::Right),
+        ];
+        let mut rng = GridRng::from_seed(14);
+        for _ in 0..200 {
+            let grid = random_cube(&mut rng);
+            for (square, cube) in pairs {
+                let square = grid.preview(square);
+                let cube = grid.preview(cube);
+                assert_eq!(square.is_some(), cube.is_some());
+                if let (Some(square), Some(cube)) = (square, cube) {
+                    assert_eq!(square.state, cube.state);
+                    assert_eq!(square.score_delta, cube.score_delta);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+
+use crate::grid::Tile;
+use crate::rng::GridRng;
+
+pub const DEFAULT_HISTORY_CAPACITY: usize = 64;
+
+/// Everything needed to put a `Grid` back exactly as it was, including the
+/// RNG so that replaying a move spawns the same tile.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub state: Vec<Vec<Tile>>,
+    pub score: u32,
+    pub moves: u32,
+    pub deadlocked: bool,
+    pub rng: GridRng,
+}
+
+/// Bounded undo and redo stacks. The oldest snapshot is dropped once
+/// `capacity` is reached.
+#[derive(Debug, Clone)]
+pub struct History {
+    capacity: usize,
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        return History {
+            capacity,
+            undo: VecDeque::new(),
+            redo: vec![],
+        };
+    }
+
+    /// Records the state before a new move. Any redo entries are discarded.
+    pub fn record(&mut self, snapshot: Snapshot) {
+        self.redo.clear();
+        self.push_undo(snapshot);
+    }
+
+    fn push_undo(&mut self, snapshot: Snapshot) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.undo.len() == self.capacity {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(snapshot);
+    }
+
+    /// Swaps `current` for the most recent undo entry.
+    pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let previous = self.undo.pop_back()?;
+        self.redo.push(current);
+        return Some(previous);
+    }
+
+    /// Swaps `current` for the most recent redo entry.
+    pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let next = self.redo.pop()?;
+        self.push_undo(current);
+        return Some(next);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        return !self.undo.is_empty();
+    }
+
+    pub fn can_redo(&self) -> bool {
+        return !self.redo.is_empty();
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        return History::new(DEFAULT_HISTORY_CAPACITY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(moves: u32) -> Snapshot {
+        return Snapshot {
+            state: vec![vec![2]],
+            score: 0,
+            moves,
+            deadlocked: false,
+            rng: GridRng::from_seed(0),
+        };
+    }
+
+    #[test]
+    fn full_history_drops_the_oldest_snapshot() {
+        let mut history = History::new(2);
+        for moves in 0..3 {
+            history.record(snapshot(moves));
+        }
+        assert_eq!(history.undo(snapshot(3)).unwrap().moves, 2);
+        assert_eq!(history.undo(snapshot(2)).unwrap().moves, 1);
+        assert!(history.undo(snapshot(1)).is_none());
+        assert_eq!(history.redo(snapshot(1)).unwrap().moves, 2);
+    }
+
+    #[test]
+    fn recording_clears_redo() {
+        let mut history = History::new(2);
+        history.record(snapshot(0));
+        history.undo(snapshot(1));
+        assert!(history.can_redo());
+        history.record(snapshot(0));
+        assert!(!history.can_redo());
+
+        let mut none = History::new(0);
+        none.record(snapshot(0));
+        assert!(!none.can_undo());
+    }
+}
@@ -0,0 +1,30 @@
+//! Game rules for 2048: sliding, merging, spawning and deadlock detection.
+//!
+//! The engine has no dependency on Bevy. Enable the `bevy` feature to derive
+//! `Resource` and `Reflect` on [`Grid`] so the game can insert it directly.
+
+#![allow(clippy::needless_return, clippy::needless_range_loop)]
+
+pub mod ai;
+pub mod bitboard;
+pub mod direction;
+pub mod grid;
+pub mod history;
+pub mod outcome;
+pub mod rng;
+pub mod rules;
+pub mod shape;
+pub mod spawn;
+
+pub use bitboard::Bitboard;
+pub use direction::{
+    CubeDirection, Direction, HexDirection, MoveDirection, CUBE_DIRECTIONS, DIRECTIONS,
+    HEX_DIRECTIONS,
+};
+pub use grid::{Grid, Tile, HOLE, WALL};
+pub use history::{History, Snapshot};
+pub use outcome::{MoveOutcome, Position, Preview, TileMerge, TileMove, TileSpawn};
+pub use rng::GridRng;
+pub use rules::{ClassicRule, FibonacciRule, MergeRule, NegativeRule, TriplesRule};
+pub use shape::Shape;
+pub use spawn::SpawnRules;
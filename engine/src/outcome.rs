@@ -0,0 +1,77 @@
+use crate::direction::{Direction, MoveDirection};
+use crate::grid::Tile;
+
+/// A cell on the grid, by row `i` and column `j`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub i: usize,
+    pub j: usize,
+}
+
+/// A tile that slid without merging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileMove {
+    pub from: Position,
+    pub to: Position,
+    pub value: Tile,
+}
+
+/// Tiles that combined into one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileMerge {
+    /// One entry per merged tile, starting with the tile nearest the edge the
+    /// move is towards.
+    pub sources: Vec<Position>,
+    pub to: Position,
+    /// Value of the resulting tile, or `0` if the tiles annihilated. `to` is
+    /// then the cell they met in, which another tile may slide into.
+    pub value: Tile,
+}
+
+/// A tile added to an empty cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileSpawn {
+    pub position: Position,
+    pub value: Tile,
+}
+
+/// The result of a move that has not been played, from `Grid::preview`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preview<D = MoveDirection> {
+    pub direction: D,
+    /// Board after sliding and merging, before any tile is spawned.
+    pub state: Vec<Vec<Tile>>,
+    pub score_delta: u32,
+}
+
+/// Everything that happened during a single call to `Grid::update`.
+///
+/// `moves` only lists tiles that changed cell. Tiles that stayed put and did
+/// not merge are omitted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveOutcome<D = MoveDirection> {
+    pub direction: D,
+    pub moves: Vec<TileMove>,
+    pub merges: Vec<TileMerge>,
+    pub spawns: Vec<TileSpawn>,
+    /// Walls placed after the move.
+    pub walls: Vec<Position>,
+    pub score_delta: u32,
+}
+
+impl<D: Direction> MoveOutcome<D> {
+    pub fn new(direction: D) -> Self {
+        return MoveOutcome {
+            direction,
+            moves: vec![],
+            merges: vec![],
+            spawns: vec![],
+            walls: vec![],
+            score_delta: 0,
+        };
+    }
+
+    pub fn has_changed(&self) -> bool {
+        return !self.moves.is_empty() || !self.merges.is_empty();
+    }
+}
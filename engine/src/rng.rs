@@ -0,0 +1,39 @@
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Seedable random number generator used for tile spawning.
+///
+/// ChaCha8 produces the same stream on every platform and `rand` release, so a
+/// seed is enough to reproduce a game.
+#[derive(Clone, Debug)]
+pub struct GridRng(ChaCha8Rng);
+
+impl GridRng {
+    pub fn from_seed(seed: u64) -> Self {
+        return GridRng(ChaCha8Rng::seed_from_u64(seed));
+    }
+}
+
+impl Default for GridRng {
+    fn default() -> Self {
+        return GridRng::from_seed(0);
+    }
+}
+
+impl RngCore for GridRng {
+    fn next_u32(&mut self) -> u32 {
+        return self.0.next_u32();
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        return self.0.next_u64();
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        return self.0.try_fill_bytes(dest);
+    }
+}
@@ -0,0 +1,177 @@
+use std::fmt::Debug;
+
+use crate::grid::Tile;
+
+/// Decides which neighbouring tiles combine and what they become.
+///
+/// Values passed to a rule are never `0`, `WALL` or `HOLE`.
+pub trait MergeRule: Debug + Send + Sync {
+    /// Number of adjacent tiles that combine in a single merge.
+    fn group_size(&self) -> usize {
+        return 2;
+    }
+
+    /// Returns the value of the combined tile if `tiles` can merge. `tiles`
+    /// holds `group_size()` values ordered from the edge the move is towards.
+    /// `Some(0)` means the tiles annihilate and leave no tile behind.
+    fn merge(&self, tiles: &[Tile]) -> Option<Tile>;
+
+    /// Points awarded for producing a tile of `merged`. Negative tiles and
+    /// annihilations score nothing by default.
+    fn score(&self, merged: Tile) -> u32 {
+        return merged.max(0) as u32;
+    }
+
+    /// Position of `value` in the rule's sequence, starting at `1` for the
+    /// smallest tile. Returns `0` for values the rule never produces. The game
+    /// uses this to pick tile colours.
+    fn rank(&self, value: Tile) -> u32;
+
+    /// True only for the classic doubling rule, which the bitboard fast path
+    /// implements.
+    fn is_classic(&self) -> bool {
+        return false;
+    }
+
+    fn clone_box(&self) -> Box<dyn MergeRule>;
+}
+
+impl Default for Box<dyn MergeRule> {
+    fn default() -> Self {
+        return Box::new(ClassicRule);
+    }
+}
+
+impl Clone for Box<dyn MergeRule> {
+    fn clone(&self) -> Self {
+        return self.clone_box();
+    }
+}
+
+/// Equal tiles combine into their double.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClassicRule;
+
+impl MergeRule for ClassicRule {
+    fn merge(&self, tiles: &[Tile]) -> Option<Tile> {
+        if tiles[0] != tiles[1] {
+            return None;
+        }
+        return tiles[0].checked_mul(2);
+    }
+
+    fn rank(&self, value: Tile) -> u32 {
+        if value < 2 || value.count_ones() != 1 {
+            return 0;
+        }
+        return value.trailing_zeros();
+    }
+
+    fn is_classic(&self) -> bool {
+        return true;
+    }
+
+    fn clone_box(&self) -> Box<dyn MergeRule> {
+        return Box::new(*self);
+    }
+}
+
+/// Consecutive Fibonacci numbers combine into their sum, so `1 + 1 = 2`,
+/// `1 + 2 = 3`, `2 + 3 = 5` and so on.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FibonacciRule;
+
+impl FibonacciRule {
+    /// Index of `value` in `1, 2, 3, 5, 8, ...`, starting at `1`.
+    fn index(value: Tile) -> Option<u32> {
+        let (mut previous, mut current): (Tile, Tile) = (1, 1);
+        let mut index = 1;
+        while current < value {
+            let next = previous.checked_add(current)?;
+            previous = current;
+            current = next;
+            index += 1;
+        }
+        return if current == value { Some(index) } else { None };
+    }
+}
+
+impl MergeRule for FibonacciRule {
+    fn merge(&self, tiles: &[Tile]) -> Option<Tile> {
+        let a = FibonacciRule::index(tiles[0])?;
+        let b = FibonacciRule::index(tiles[1])?;
+        if a.abs_diff(b) > 1 || (a == b && tiles[0] != 1) {
+            return None;
+        }
+        return tiles[0].checked_add(tiles[1]);
+    }
+
+    fn rank(&self, value: Tile) -> u32 {
+        return FibonacciRule::index(value).unwrap_or(0);
+    }
+
+    fn clone_box(&self) -> Box<dyn MergeRule> {
+        return Box::new(*self);
+    }
+}
+
+/// Three equal tiles in a row combine into their triple, so tiles are powers
+/// of three.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TriplesRule;
+
+impl MergeRule for TriplesRule {
+    fn group_size(&self) -> usize {
+        return 3;
+    }
+
+    fn merge(&self, tiles: &[Tile]) -> Option<Tile> {
+        if tiles[0] != tiles[1] || tiles[1] != tiles[2] {
+            return None;
+        }
+        return tiles[0].checked_mul(3);
+    }
+
+    fn rank(&self, value: Tile) -> u32 {
+        let mut power: Tile = 3;
+        let mut rank = 1;
+        while power < value {
+            let Some(next) = power.checked_mul(3) else {
+                return 0;
+            };
+            power = next;
+            rank += 1;
+        }
+        return if power == value { rank } else { 0 };
+    }
+
+    fn clone_box(&self) -> Box<dyn MergeRule> {
+        return Box::new(*self);
+    }
+}
+
+/// Classic doubling with negative tiles. Equal tiles double whatever their
+/// sign, and a tile meeting its negative annihilates, clearing both cells.
+///
+/// Only positive tiles score, so merging negatives and annihilating earn
+/// nothing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NegativeRule;
+
+impl MergeRule for NegativeRule {
+    fn merge(&self, tiles: &[Tile]) -> Option<Tile> {
+        if tiles[0].checked_neg() == Some(tiles[1]) {
+            return Some(0);
+        }
+        return ClassicRule.merge(tiles);
+    }
+
+    /// Negative tiles rank by their size, so `-4` ranks with `4`.
+    fn rank(&self, value: Tile) -> u32 {
+        return ClassicRule.rank(value.saturating_abs());
+    }
+
+    fn clone_box(&self) -> Box<dyn MergeRule> {
+        return Box::new(*self);
+    }
+}
@@ -0,0 +1,128 @@
+#[cfg(feature = "bevy")]
+use bevy_reflect::Reflect;
+
+use crate::outcome::Position;
+
+/// The layout of a board and how it maps onto the grid's matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
+pub enum Shape {
+    Rectangle {
+        rows: usize,
+        columns: usize,
+    },
+    /// A hexagon with `radius` cells from the centre to each side, stored in
+    /// axial coordinates. Cell `(q, r)` lives at row `r + radius` and column
+    /// `q + radius`, and the two matrix corners outside the hexagon are holes.
+    Hex {
+        radius: usize,
+    },
+    /// A `size` x `size` x `size` cube. Its layers are stored side by side, so
+    /// layer `k` occupies columns `k * size..(k + 1) * size` of the matrix.
+    Cube {
+        size: usize,
+    },
+    /// A rectangle whose opposite edges join up, so a tile sliding off one
+    /// edge comes back on the other.
+    Torus {
+        rows: usize,
+        columns: usize,
+    },
+}
+
+impl Shape {
+    /// Rows and columns of the matrix holding the board.
+    pub fn dimensions(self) -> (usize, usize) {
+        match self {
+            Shape::Rectangle { rows, columns } | Shape::Torus { rows, columns } => (rows, columns),
+            Shape::Hex { radius } => (2 * radius + 1, 2 * radius + 1),
+            Shape::Cube { size } => (size, size * size),
+        }
+    }
+
+    /// Whether a matrix cell is part of the board.
+    pub fn contains(self, position: Position) -> bool {
+        match self {
+            Shape::Rectangle { rows, columns } | Shape::Torus { rows, columns } => {
+                position.i < rows && position.j < columns
+            }
+            Shape::Cube { size } => position.i < size && position.j < size * size,
+            Shape::Hex { radius } => self
+                .axial(position)
+                .is_some_and(|(q, r)| (q + r).unsigned_abs() <= radius),
+        }
+    }
+
+    /// Whether rows and columns wrap round. Lines on such a board are rings,
+    /// though [`Shape::neighbour`] still stops at the matrix edge.
+    pub fn wraps(self) -> bool {
+        return matches!(self, Shape::Torus { .. });
+    }
+
+    /// The cell one `step` of rows, columns and layers away from `position`,
+    /// or `None` if that leaves the board's matrix.
+    pub fn neighbour(self, position: Position, step: (isize, isize, isize)) -> Option<Position> {
+        let (di, dj, dk) = step;
+        if let Shape::Cube { size } = self {
+            let (layer, cell) = self.layer(position)?;
+            let i = cell.i.checked_add_signed(di)?;
+            let j = cell.j.checked_add_signed(dj)?;
+            let k = layer.checked_add_signed(dk)?;
+            if i >= size || j >= size || k >= size {
+                return None;
+            }
+            return Some(Position { i, j: k * size + j });
+        }
+
+        let (rows, columns) = self.dimensions();
+        let i = position.i.checked_add_signed(di)?;
+        let j = position.j.checked_add_signed(dj)?;
+        if dk != 0 || i >= rows || j >= columns {
+            return None;
+        }
+        return Some(Position { i, j });
+    }
+
+    /// Layer of a cube cell and its position within that layer, or `None` on
+    /// other shapes.
+    pub fn layer(self, position: Position) -> Option<(usize, Position)> {
+        let Shape::Cube { size } = self else {
+            return None;
+        };
+        if !self.contains(position) {
+            return None;
+        }
+        return Some((
+            position.j / size,
+            Position {
+                i: position.i,
+                j: position.j % size,
+            },
+        ));
+    }
+
+    /// Axial `(q, r)` coordinates of a hex cell relative to the centre, or
+    /// `None` on other shapes.
+    pub fn axial(self, position: Position) -> Option<(isize, isize)> {
+        let Shape::Hex { radius } = self else {
+            return None;
+        };
+        let size = 2 * radius + 1;
+        if position.i >= size || position.j >= size {
+            return None;
+        }
+        return Some((
+            position.j as isize - radius as isize,
+            position.i as isize - radius as isize,
+        ));
+    }
+}
+
+impl Default for Shape {
+    fn default() -> Self {
+        return Shape::Rectangle {
+            rows: 0,
+            columns: 0,
+        };
+    }
+}
@@ -0,0 +1,67 @@
+#[cfg(feature = "bevy")]
+use bevy_reflect::Reflect;
+
+use rand::Rng;
+
+use crate::grid::Tile;
+
+/// Controls which tiles appear, how many and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
+pub struct SpawnRules {
+    /// `(value, weight)` pairs. A value is picked with probability
+    /// `weight / total weight`.
+    pub values: Vec<(Tile, u32)>,
+    /// Tiles placed on an empty board at the start of a game.
+    pub starting_tiles: u8,
+    /// Tiles placed after every move that changes the board.
+    pub tiles_per_move: u8,
+    /// Only spawn on the edge the last move slid away from, falling back to
+    /// any empty cell when that edge is full.
+    pub edge_only: bool,
+    /// Walls placed on an empty board at the start of a game.
+    pub starting_walls: u8,
+    /// Percent chance that a move which changes the board also places a wall.
+    pub wall_chance: u32,
+    /// No more walls appear once the board holds this many.
+    pub max_walls: u8,
+}
+
+impl SpawnRules {
+    /// The standard game: 90% twos, 10% fours, two starting tiles and one
+    /// tile per move.
+    pub fn classic() -> Self {
+        return SpawnRules {
+            values: vec![(2, 9), (4, 1)],
+            starting_tiles: 2,
+            tiles_per_move: 1,
+            edge_only: false,
+            starting_walls: 0,
+            wall_chance: 0,
+            max_walls: 0,
+        };
+    }
+
+    /// Picks a tile value from the weighted table, or `None` if it is empty or
+    /// every weight is zero.
+    pub fn pick_value<R: Rng>(&self, rng: &mut R) -> Option<Tile> {
+        let total: u32 = self.values.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = rng.gen_range(0..total);
+        for (value, weight) in self.values.iter() {
+            if roll < *weight {
+                return Some(*value);
+            }
+            roll -= weight;
+        }
+        return None;
+    }
+}
+
+impl Default for SpawnRules {
+    fn default() -> Self {
+        return SpawnRules::classic();
+    }
+}
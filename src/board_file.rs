@@ -0,0 +1,279 @@
+use std::{fmt, fs, path::PathBuf};
+
+use bevy::ecs::system::Resource;
+use two_thousand_forty_eight_engine::Tile;
+
+use crate::constants::BOARD_FILE;
+
+/// Where starting positions are loaded from. The path comes from the first
+/// command line argument, or [`BOARD_FILE`] when there is none.
+#[derive(Resource)]
+pub struct BoardFile {
+    pub path: PathBuf,
+    /// Load the board as soon as the game starts, because its path was given
+    /// on the command line.
+    pub load_on_start: bool,
+}
+
+impl BoardFile {
+    pub fn from_args() -> Self {
+        match std::env::args_os().nth(1) {
+            Some(path) => {
+                return BoardFile {
+                    path: PathBuf::from(path),
+                    load_on_start: true,
+                };
+            }
+            None => {
+                return BoardFile {
+                    path: PathBuf::from(BOARD_FILE),
+                    load_on_start: false,
+                };
+            }
+        }
+    }
+
+    /// Reads the board and checks that it fits a `rows` x `columns` grid.
+    ///
+    /// Files ending in `.ron` hold a list of rows, such as
+    /// `[[2, 4, 0, 0], ...]`. Any other file is plain text with one row per
+    /// line, values separated by spaces, and `.` or `0` for an empty cell.
+    /// Blank lines and lines starting with `#` are skipped.
+    pub fn read(&self, rows: usize, columns: usize) -> Result<Vec<Vec<Tile>>, BoardFileError> {
+        let text = fs::read_to_string(&self.path).map_err(|error| BoardFileError::Io {
+            path: self.path.clone(),
+            message: error.to_string(),
+        })?;
+        let is_ron = self
+            .path
+            .extension()
+            .is_some_and(|extension| extension == "ron");
+        let state = if is_ron {
+            ron::from_str(&text).map_err(|error| BoardFileError::Ron(error.to_string()))?
+        } else {
+            parse_text(&text)?
+        };
+        validate(&state, rows, columns)?;
+        return Ok(state);
+    }
+}
+
+#[derive(Debug)]
+pub enum BoardFileError {
+    Io {
+        path: PathBuf,
+        message: String,
+    },
+    Ron(String),
+    /// A plain text value that is not a number. Lines count from 1.
+    Token {
+        line: usize,
+        token: String,
+    },
+    Rows {
+        expected: usize,
+        found: usize,
+    },
+    /// A row of the wrong length. Rows count from 1.
+    Columns {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A value that is not a power of two. Rows and columns count from 1.
+    Value {
+        row: usize,
+        column: usize,
+        value: Tile,
+    },
+    NoTiles,
+}
+
+impl fmt::Display for BoardFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BoardFileError::Io { path, message } => {
+                write!(f, "Could not read {}: {message}", path.display())
+            }
+            BoardFileError::Ron(message) => write!(f, "Invalid RON: {message}"),
+            BoardFileError::Token { line, token } => {
+                write!(f, "Line {line}: \"{token}\" is not a number")
+            }
+            BoardFileError::Rows { expected, found } => {
+                write!(f, "Expected {expected} rows, found {found}")
+            }
+            BoardFileError::Columns {
+                row,
+                expected,
+                found,
+            } => write!(f, "Row {row}: expected {expected} columns, found {found}"),
+            BoardFileError::Value { row, column, value } => write!(
+                f,
+                "Row {row}, column {column}: {value} is not a power of two"
+            ),
+            BoardFileError::NoTiles => write!(f, "The board has no tiles"),
+        }
+    }
+}
+
+fn parse_text(text: &str) -> Result<Vec<Vec<Tile>>, BoardFileError> {
+    let mut state = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let row = line
+            .split_whitespace()
+            .map(|token| match token {
+                "." => Ok(0),
+                _ => token.parse().map_err(|_| BoardFileError::Token {
+                    line: index + 1,
+                    token: token.to_string(),
+                }),
+            })
+            .collect::<Result<Vec<Tile>, BoardFileError>>()?;
+        state.push(row);
+    }
+    return Ok(state);
+}
+
+/// Checks the board's dimensions, then that every tile is a power of two
+/// from 2 up.
+fn validate(state: &[Vec<Tile>], rows: usize, columns: usize) -> Result<(), BoardFileError> {
+    if state.len() != rows {
+        return Err(BoardFileError::Rows {
+            expected: rows,
+            found: state.len(),
+        });
+    }
+    for (i, row) in state.iter().enumerate() {
+        if row.len() != columns {
+            return Err(BoardFileError::Columns {
+                row: i + 1,
+                expected: columns,
+                found: row.len(),
+            });
+        }
+        for (j, value) in row.iter().enumerate() {
+            if *value != 0 && (*value < 2 || value.count_ones() != 1) {
+                return Err(BoardFileError::Value {
+                    row: i + 1,
+                    column: j + 1,
+                    value: *value,
+                });
+            }
+        }
+    }
+    if state.iter().flatten().all(|value| *value == 0) {
+        return Err(BoardFileError::NoTiles);
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `text` to a file with `extension` and reads it as a 4x4 board.
+    fn read(name: &str, extension: &str, text: &str) -> Result<Vec<Vec<Tile>>, BoardFileError> {
+        let path = std::env::temp_dir().join(format!(
+            "board-file-test-{}-{name}.{extension}",
+            std::process::id()
+        ));
+        fs::write(&path, text).unwrap();
+        let board_file = BoardFile {
+            path: path.clone(),
+            load_on_start: false,
+        };
+        let result = board_file.read(4, 4);
+        fs::remove_file(&path).unwrap();
+        return result;
+    }
+
+    #[test]
+    fn reads_plain_text_and_ron() {
+        let text = "# A corner game\n2 4 . .\n\n0 0 0 0\n. . . .\n. . . 2048\n";
+        let expected = vec![
+            vec![2, 4, 0, 0],
+            vec![0, 0, 0, 0],
+            vec![0, 0, 0, 0],
+            vec![0, 0, 0, 2048],
+        ];
+        assert_eq!(read("text", "txt", text).unwrap(), expected);
+
+        let ron = "[[2, 4, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 2048]]";
+        assert_eq!(read("ron", "ron", ron).unwrap(), expected);
+    }
+
+    #[test]
+    fn reports_what_is_wrong() {
+        let board = |rows: &[&str]| rows.join("\n");
+        assert!(matches!(
+            read("token", "txt", &board(&["2 . . .", ". x . .", ". . . .", ". . . ."])),
+            Err(BoardFileError::Token { line: 2, token }) if token == "x"
+        ));
+        assert!(matches!(
+            read("rows", "txt", &board(&["2 . . .", ". . . ."])),
+            Err(BoardFileError::Rows {
+                expected: 4,
+                found: 2
+            })
+        ));
+        assert!(matches!(
+            read(
+                "columns",
+                "txt",
+                &board(&["2 . . .", ". . .", ". . . .", ". . . ."])
+            ),
+            Err(BoardFileError::Columns {
+                row: 2,
+                expected: 4,
+                found: 3
+            })
+        ));
+        assert!(matches!(
+            read(
+                "value",
+                "txt",
+                &board(&["2 . . .", ". . . .", ". . 3 .", ". . . ."])
+            ),
+            Err(BoardFileError::Value {
+                row: 3,
+                column: 3,
+                value: 3
+            })
+        ));
+        assert!(matches!(
+            read(
+                "negative",
+                "txt",
+                &board(&["2 . . .", ". . . .", ". . . .", ". -2 . ."])
+            ),
+            Err(BoardFileError::Value {
+                row: 4,
+                column: 2,
+                value: -2
+            })
+        ));
+        assert!(matches!(
+            read(
+                "empty",
+                "txt",
+                &board(&[". . . .", ". . . .", ". . . .", ". . . ."])
+            ),
+            Err(BoardFileError::NoTiles)
+        ));
+        assert!(matches!(
+            read("ron-error", "ron", "[[2, 4"),
+            Err(BoardFileError::Ron(_))
+        ));
+
+        let missing = BoardFile {
+            path: PathBuf::from("no-such-board.txt"),
+            load_on_start: false,
+        };
+        assert!(matches!(missing.read(4, 4), Err(BoardFileError::Io { .. })));
+    }
+}
+
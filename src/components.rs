@@ -1,4 +1,8 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, tasks::Task};
+use two_thousand_forty_eight_engine::{MoveDirection, Tile};
+
+#[derive(Component)]
+pub struct Board;
 
 #[derive(Component)]
 pub struct TileColour;
@@ -12,6 +16,31 @@ pub struct TilePosition {
 #[derive(Component)]
 pub struct Scoreboard;
 
+#[derive(Component)]
+pub struct CountdownDisplay;
+
+#[derive(Component)]
+pub struct MovesDisplay;
+
+/// Text over the board showing the suggested move.
+#[derive(Component)]
+pub struct HintArrow;
+
+/// A hint being searched for in the background, and the board it is for.
+#[derive(Component)]
+pub struct HintTask {
+    pub task: Task<Option<MoveDirection>>,
+    pub state: Vec<Vec<Tile>>,
+}
+
+/// The autoplay agent's next move being searched for, and the board it is
+/// for.
+#[derive(Component)]
+pub struct AutoplayTask {
+    pub task: Task<Option<MoveDirection>>,
+    pub state: Vec<Vec<Tile>>,
+}
+
 #[derive(Component)]
 pub struct Popup;
 
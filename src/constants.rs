@@ -1,8 +1,34 @@
 use bevy::prelude::*;
+use two_thousand_forty_eight_engine::Tile;
 
-pub const GRID_SIZE: usize = 4;
+pub const GRID_ROWS: usize = 4;
+pub const GRID_COLUMNS: usize = 4;
+pub const TARGET_TILE: u32 = 2048;
+/// Time limits in seconds offered for time attack games.
+pub const TIME_LIMITS: [u32; 2] = [60, 180];
+/// Merges into a tile at least this large earn bonus time.
+pub const TIME_BONUS_TILE: Tile = 256;
+pub const TIME_BONUS_SECONDS: f32 = 2.0;
+/// Move-limited objectives as `(target tile, move limit)`. Without a limit
+/// the aim is to reach the target in as few moves as possible.
+pub const OBJECTIVES: [(u32, Option<u32>); 2] = [(256, Some(150)), (512, None)];
+/// Undos allowed per game until the player picks another limit. `None` is
+/// unlimited and `Some(0)` is purist mode.
+pub const UNDO_LIMIT: Option<u32> = None;
+/// Undo limits the menu cycles through.
+pub const UNDO_LIMITS: [Option<u32>; 4] = [None, Some(3), Some(1), Some(0)];
+/// How fast autoplay moves when it is left running.
+pub const AUTOPLAY_MOVES_PER_SECOND: f32 = 4.0;
 pub const TILE_SIZE: Vec2 = Vec2::new(128.0, 128.0);
 pub const TILE_MARGIN: f32 = 10.0;
+/// Cells from the centre of the hex board to each side.
+pub const HEX_RADIUS: usize = 2;
+pub const HEX_TILE_SIZE: Vec2 = Vec2::new(96.0, 96.0);
+pub const CUBE_SIZE: usize = 4;
+pub const CUBE_TILE_SIZE: Vec2 = Vec2::new(56.0, 56.0);
+pub const CUBE_TILE_MARGIN: f32 = 6.0;
+/// Space between the cube's layers when they are laid out side by side.
+pub const CUBE_LAYER_GAP: f32 = 40.0;
 pub const BACKGROUND_COLOUR: Color = Color::rgb(51.0 / 255.0, 51.0 / 255.0, 51.0 / 255.0);
 pub const BUTTON_GREEN: Color = Color::rgb(31.0 / 255.0, 148.0 / 255.0, 24.0 / 255.0);
 pub const BUTTON_GREEN_HOVER: Color = Color::rgb(31.0 / 255.0, 175.0 / 255.0, 24.0 / 255.0);
@@ -10,5 +36,64 @@ pub const BUTTON_GREEN_PRESSED: Color = Color::rgb(35.0 / 255.0, 111.0 / 255.0,
 pub const BUTTON_RED: Color = Color::rgb(148.0 / 255.0, 31.0 / 255.0, 24.0 / 255.0);
 pub const BUTTON_RED_HOVER: Color = Color::rgb(175.0 / 255.0, 31.0 / 255.0, 24.0 / 255.0);
 pub const BUTTON_RED_PRESSED: Color = Color::rgb(111.0 / 255.0, 35.0 / 255.0, 31.0 / 255.0);
+/// Colour of the suggested move shown over the board.
+pub const HINT_ARROW_COLOUR: Color = Color::rgba(1.0, 1.0, 1.0, 0.75);
+/// Border drawn round boards whose edges wrap.
+pub const WRAP_EDGE_COLOUR: Color = Color::rgb(64.0 / 255.0, 170.0 / 255.0, 200.0 / 255.0);
+pub const WRAP_EDGE_WIDTH: f32 = 4.0;
+pub const WALL_COLOUR: Color = Color::rgb(110.0 / 255.0, 110.0 / 255.0, 120.0 / 255.0);
+pub const EMPTY_TILE_COLOUR: Color = Color::rgb(25.0 / 255.0, 25.0 / 255.0, 25.0 / 255.0);
+pub const OVERFLOW_TILE_COLOUR: Color = Color::rgb(160.0 / 255.0, 0.0 / 255.0, 0.0 / 255.0);
+/// Tile colours by rank, for rules where tiles grow by doubling or by
+/// Fibonacci steps.
+pub const TILE_COLOURS: [Color; 14] = [
+    Color::rgb(209.0 / 255.0, 205.0 / 255.0, 100.0 / 255.0),
+    Color::rgb(209.0 / 255.0, 160.0 / 255.0, 69.0 / 255.0),
+    Color::rgb(209.0 / 255.0, 93.0 / 255.0, 54.0 / 255.0),
+    Color::rgb(209.0 / 255.0, 54.0 / 255.0, 54.0 / 255.0),
+    Color::rgb(209.0 / 255.0, 65.0 / 255.0, 144.0 / 255.0),
+    Color::rgb(162.0 / 255.0, 65.0 / 255.0, 191.0 / 255.0),
+    Color::rgb(100.0 / 255.0, 57.0 / 255.0, 191.0 / 255.0),
+    Color::rgb(57.0 / 255.0, 60.0 / 255.0, 191.0 / 255.0),
+    Color::rgb(86.0 / 255.0, 127.0 / 255.0, 270.0 / 255.0),
+    Color::rgb(46.0 / 255.0, 164.0 / 255.0, 232.0 / 255.0),
+    Color::rgb(41.0 / 255.0, 212.0 / 255.0, 184.0 / 255.0),
+    Color::rgb(47.0 / 255.0, 194.0 / 255.0, 81.0 / 255.0),
+    Color::rgb(66.0 / 255.0, 143.0 / 255.0, 30.0 / 255.0),
+    Color::rgb(182.0 / 255.0, 227.0 / 255.0, 68.0 / 255.0),
+];
+/// Tile colours by rank for powers of three, which climb much faster.
+pub const TRIPLES_TILE_COLOURS: [Color; 10] = [
+    Color::rgb(92.0 / 255.0, 184.0 / 255.0, 178.0 / 255.0),
+    Color::rgb(58.0 / 255.0, 150.0 / 255.0, 196.0 / 255.0),
+    Color::rgb(52.0 / 255.0, 105.0 / 255.0, 196.0 / 255.0),
+    Color::rgb(86.0 / 255.0, 70.0 / 255.0, 190.0 / 255.0),
+    Color::rgb(136.0 / 255.0, 62.0 / 255.0, 184.0 / 255.0),
+    Color::rgb(184.0 / 255.0, 58.0 / 255.0, 160.0 / 255.0),
+    Color::rgb(214.0 / 255.0, 72.0 / 255.0, 110.0 / 255.0),
+    Color::rgb(226.0 / 255.0, 112.0 / 255.0, 60.0 / 255.0),
+    Color::rgb(232.0 / 255.0, 170.0 / 255.0, 48.0 / 255.0),
+    Color::rgb(222.0 / 255.0, 214.0 / 255.0, 80.0 / 255.0),
+];
+/// Tile colours by the size of a negative tile, kept cold and dark so they
+/// stand out from the positive ramp.
+pub const NEGATIVE_TILE_COLOURS: [Color; 6] = [
+    Color::rgb(96.0 / 255.0, 112.0 / 255.0, 128.0 / 255.0),
+    Color::rgb(78.0 / 255.0, 92.0 / 255.0, 112.0 / 255.0),
+    Color::rgb(62.0 / 255.0, 74.0 / 255.0, 98.0 / 255.0),
+    Color::rgb(48.0 / 255.0, 56.0 / 255.0, 84.0 / 255.0),
+    Color::rgb(36.0 / 255.0, 40.0 / 255.0, 70.0 / 255.0),
+    Color::rgb(26.0 / 255.0, 26.0 / 255.0, 56.0 / 255.0),
+];
 pub const FONT_PATH: &str = "fonts/FiraSans-Bold.ttf";
+/// Folder for saved data inside the platform's user data directory.
+pub const SAVE_DIRECTORY: &str = "two_thousand_forty_eight";
+pub const DAILY_FILE: &str = "daily.ron";
+/// Starting position loaded from the menu when no path is given on the
+/// command line.
+pub const BOARD_FILE: &str = "board.txt";
+/// Weights for the n-tuple solver, loaded when it is picked in the menu. The
+/// engine's `ntuple` trainer writes its checkpoints elsewhere, so copy one
+/// here once it plays well enough.
+pub const NTUPLE_FILE: &str = "ntuple.bin";
 
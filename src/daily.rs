@@ -0,0 +1,103 @@
+use std::{fs, path::PathBuf};
+
+use bevy::{ecs::system::Resource, reflect::Reflect};
+use chrono::{Datelike, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{DAILY_FILE, SAVE_DIRECTORY};
+
+/// A calendar day in the player's time zone. Everyone gets the same board on
+/// the same date, and it changes at their own midnight.
+#[derive(Serialize, Deserialize, Reflect, PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub fn today() -> Self {
+        let today = Local::now().date_naive();
+        return Date {
+            year: today.year(),
+            month: today.month(),
+            day: today.day(),
+        };
+    }
+
+    /// Seed for the day's board. Every player gets the same spawns on the
+    /// same day.
+    pub fn seed(&self) -> u64 {
+        return self.year as u64 * 10_000 + self.month as u64 * 100 + self.day as u64;
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DailyResult {
+    pub date: Date,
+    pub score: u32,
+    pub max_tile: u32,
+    pub moves: u32,
+    /// False while the attempt is still being played.
+    pub finished: bool,
+}
+
+/// Daily attempts played on this machine, saved to [`DAILY_FILE`].
+#[derive(Resource, Default)]
+pub struct DailyResults {
+    pub results: Vec<DailyResult>,
+}
+
+impl DailyResults {
+    /// Reads the saved results, starting afresh if there are none or the
+    /// file cannot be read.
+    pub fn load() -> Self {
+        let results = fs::read_to_string(save_path())
+            .ok()
+            .and_then(|text| ron::from_str(&text).ok())
+            .unwrap_or_default();
+        return DailyResults { results };
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = save_path();
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+        }
+        let text = ron::ser::to_string_pretty(&self.results, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())?;
+        return fs::write(path, text).map_err(|error| error.to_string());
+    }
+
+    pub fn get(&self, date: Date) -> Option<&DailyResult> {
+        return self.results.iter().find(|result| result.date == date);
+    }
+
+    /// Stores `result`, replacing any earlier one for the same day.
+    pub fn record(&mut self, result: DailyResult) {
+        self.results.retain(|saved| saved.date != result.date);
+        self.results.push(result);
+    }
+}
+
+/// The platform's per-user data directory, falling back to the working
+/// directory.
+fn save_path() -> PathBuf {
+    let directory = if let Some(data) = std::env::var_os("XDG_DATA_HOME") {
+        PathBuf::from(data)
+    } else if let Some(app_data) = std::env::var_os("APPDATA") {
+        PathBuf::from(app_data)
+    } else if let Some(home) = std::env::var_os("HOME") {
+        PathBuf::from(home).join(".local").join("share")
+    } else {
+        PathBuf::from(".")
+    };
+    return directory.join(SAVE_DIRECTORY).join(DAILY_FILE);
+}
+
@@ -1,8 +1,10 @@
 use crate::components::*;
 use crate::constants::*;
+use crate::daily::DailyResult;
 use crate::enums::*;
 
 use bevy::prelude::*;
+use two_thousand_forty_eight_engine::{Tile, WALL};
 
 pub struct ButtonComponents {
     pub container: (ButtonBundle, ButtonColours, Name),
@@ -14,9 +16,25 @@ pub struct TileComponents {
     pub text: (TextBundle, TilePosition),
 }
 
+pub struct HintArrowComponents {
+    pub container: NodeBundle,
+    pub text: (TextBundle, HintArrow),
+}
+
+pub struct HexBoardComponents {
+    pub container: NodeBundle,
+    pub cells: NodeBundle,
+}
+
+pub struct CubeBoardComponents {
+    pub container: NodeBundle,
+    pub layers: Vec<NodeBundle>,
+}
+
 pub struct PopupComponents {
     pub container: (NodeBundle, Popup),
     pub text: TextBundle,
+    pub button_list: NodeBundle,
     pub buttons: Vec<ButtonComponents>,
 }
 
@@ -52,16 +70,18 @@ pub fn new_button(text: &String, font: &Handle<Font>, colours: ButtonColours) ->
     };
 }
 
-pub fn new_board() -> NodeBundle {
-    return NodeBundle {
+/// Boards whose edges wrap are shrunk to fit their tiles and outlined, so the
+/// border marks where tiles leave and come back.
+pub fn new_board(rows: usize, columns: usize, wraps: bool) -> NodeBundle {
+    let mut board = NodeBundle {
         style: Style {
             width: Val::Percent(100.0),
             height: Val::Percent(100.0),
             display: Display::Grid,
             justify_content: JustifyContent::Center,
             align_content: AlignContent::Center,
-            grid_template_columns: vec![GridTrack::px(TILE_SIZE.y); GRID_SIZE],
-            grid_template_rows: vec![GridTrack::px(TILE_SIZE.x); GRID_SIZE],
+            grid_template_columns: vec![GridTrack::px(TILE_SIZE.x); columns],
+            grid_template_rows: vec![GridTrack::px(TILE_SIZE.y); rows],
             row_gap: Val::Px(TILE_MARGIN),
             column_gap: Val::Px(TILE_MARGIN),
             ..default()
@@ -69,9 +89,87 @@ pub fn new_board() -> NodeBundle {
         background_color: BACKGROUND_COLOUR.into(),
         ..default()
     };
+
+    if wraps {
+        board.style.width = Val::Auto;
+        board.style.height = Val::Auto;
+        board.style.margin = UiRect::all(Val::Auto);
+        board.style.padding = UiRect::all(Val::Px(TILE_MARGIN));
+        board.style.border = UiRect::all(Val::Px(WRAP_EDGE_WIDTH));
+        board.border_color = WRAP_EDGE_COLOUR.into();
+    }
+
+    return board;
 }
 
-pub fn new_tile(value: u32, tile_position: TilePosition, font: &Handle<Font>) -> TileComponents {
+/// Hex cells cannot sit on a CSS grid, so the board is a fixed-size area that
+/// tiles are placed on absolutely by `new_hex_tile`.
+pub fn new_hex_board(radius: usize) -> HexBoardComponents {
+    let cells = (2 * radius + 1) as f32;
+    return HexBoardComponents {
+        container: NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: BACKGROUND_COLOUR.into(),
+            ..default()
+        },
+        cells: NodeBundle {
+            style: Style {
+                width: Val::Px(cells * (HEX_TILE_SIZE.x + TILE_MARGIN) - TILE_MARGIN),
+                height: Val::Px(cells * (HEX_TILE_SIZE.y + TILE_MARGIN) - TILE_MARGIN),
+                ..default()
+            },
+            ..default()
+        },
+    };
+}
+
+/// The cube's layers as small boards in a row, front layer on the left.
+pub fn new_cube_board(size: usize) -> CubeBoardComponents {
+    let mut layers = vec![];
+    for _k in 0..size {
+        layers.push(NodeBundle {
+            style: Style {
+                display: Display::Grid,
+                grid_template_columns: vec![GridTrack::px(CUBE_TILE_SIZE.x); size],
+                grid_template_rows: vec![GridTrack::px(CUBE_TILE_SIZE.y); size],
+                row_gap: Val::Px(CUBE_TILE_MARGIN),
+                column_gap: Val::Px(CUBE_TILE_MARGIN),
+                ..default()
+            },
+            ..default()
+        });
+    }
+
+    return CubeBoardComponents {
+        container: NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(CUBE_LAYER_GAP),
+                ..default()
+            },
+            background_color: BACKGROUND_COLOUR.into(),
+            ..default()
+        },
+        layers,
+    };
+}
+
+pub fn new_tile(value: Tile, tile_position: TilePosition, font: &Handle<Font>) -> TileComponents {
+    let (text, background_color) = if value == WALL {
+        (String::new(), WALL_COLOUR.into())
+    } else {
+        (value.to_string(), BackgroundColor::default())
+    };
+
     return TileComponents {
         container: (
             NodeBundle {
@@ -83,6 +181,7 @@ pub fn new_tile(value: u32, tile_position: TilePosition, font: &Handle<Font>) ->
                     align_items: AlignItems::Center,
                     ..default()
                 },
+                background_color,
                 ..default()
             },
             TileColour,
@@ -91,7 +190,7 @@ pub fn new_tile(value: u32, tile_position: TilePosition, font: &Handle<Font>) ->
         text: (
             TextBundle {
                 text: Text::from_section(
-                    value.to_string(),
+                    text,
                     TextStyle {
                         font: font.clone(),
                         font_size: 48.0,
@@ -106,6 +205,40 @@ pub fn new_tile(value: u32, tile_position: TilePosition, font: &Handle<Font>) ->
     };
 }
 
+/// A tile for the hex cell at axial `(q, r)`. Columns are offset by half a
+/// tile per step of `q`, so each column lines up with its six neighbours.
+pub fn new_hex_tile(
+    value: Tile,
+    tile_position: TilePosition,
+    (q, r): (isize, isize),
+    radius: usize,
+    font: &Handle<Font>,
+) -> TileComponents {
+    let mut tile_components = new_tile(value, tile_position, font);
+    let step = HEX_TILE_SIZE + TILE_MARGIN;
+    let style = &mut tile_components.container.0.style;
+    style.position_type = PositionType::Absolute;
+    style.width = Val::Px(HEX_TILE_SIZE.x);
+    style.height = Val::Px(HEX_TILE_SIZE.y);
+    style.left = Val::Px((q + radius as isize) as f32 * step.x);
+    style.top = Val::Px((r as f32 + q as f32 / 2.0 + radius as f32) * step.y);
+    tile_components.text.0.text.sections[0].style.font_size = 32.0;
+    return tile_components;
+}
+
+pub fn new_cube_tile(
+    value: Tile,
+    tile_position: TilePosition,
+    font: &Handle<Font>,
+) -> TileComponents {
+    let mut tile_components = new_tile(value, tile_position, font);
+    let style = &mut tile_components.container.0.style;
+    style.width = Val::Px(CUBE_TILE_SIZE.x);
+    style.height = Val::Px(CUBE_TILE_SIZE.y);
+    tile_components.text.0.text.sections[0].style.font_size = 20.0;
+    return tile_components;
+}
+
 pub fn new_scoreboard(value: u32, font: &Handle<Font>) -> (TextBundle, Scoreboard) {
     return (
         TextBundle {
@@ -131,6 +264,89 @@ pub fn new_scoreboard(value: u32, font: &Handle<Font>) -> (TextBundle, Scoreboar
     );
 }
 
+pub fn new_countdown_display(font: &Handle<Font>) -> (TextBundle, CountdownDisplay) {
+    return (
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 32.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_alignment(TextAlignment::Right),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(25.0),
+                right: Val::Px(30.0),
+                ..default()
+            },
+            z_index: ZIndex::Global(1),
+            ..default()
+        },
+        CountdownDisplay,
+    );
+}
+
+pub fn new_moves_display(font: &Handle<Font>) -> (TextBundle, MovesDisplay) {
+    return (
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 32.0,
+                    color: Color::WHITE,
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(25.0),
+                left: Val::Px(30.0),
+                ..default()
+            },
+            z_index: ZIndex::Global(1),
+            ..default()
+        },
+        MovesDisplay,
+    );
+}
+
+/// An empty overlay covering the board, centring whatever hint it is given.
+pub fn new_hint_arrow(font: &Handle<Font>) -> HintArrowComponents {
+    return HintArrowComponents {
+        container: NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(0.0),
+                top: Val::Px(0.0),
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            z_index: ZIndex::Local(1),
+            ..default()
+        },
+        text: (
+            TextBundle {
+                text: Text::from_section(
+                    "",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 160.0,
+                        color: HINT_ARROW_COLOUR,
+                    },
+                ),
+                ..default()
+            },
+            HintArrow,
+        ),
+    };
+}
+
 pub fn new_popup(
     text: &String,
     font: &Handle<Font>,
@@ -173,65 +389,231 @@ pub fn new_popup(
             .with_alignment(TextAlignment::Center),
             ..default()
         },
+        button_list: NodeBundle {
+            style: Style {
+                max_width: Val::Px(640.0),
+                flex_direction: FlexDirection::Row,
+                flex_wrap: FlexWrap::Wrap,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(20.0),
+                column_gap: Val::Px(20.0),
+                ..default()
+            },
+            ..default()
+        },
         buttons: button_components,
     };
 }
 
-pub fn new_gameover_popup(font: &Handle<Font>) -> PopupComponents {
+/// The Undo button is left out when there is no undo left to take.
+pub fn new_gameover_popup(font: &Handle<Font>, can_undo: bool) -> PopupComponents {
+    let mut buttons = vec![];
+    if can_undo {
+        buttons.push((
+            ButtonType::Undo.to_string(),
+            ButtonColours {
+                pressed: BUTTON_GREEN_PRESSED,
+                hover: BUTTON_GREEN_HOVER,
+                none: BUTTON_GREEN,
+            },
+        ));
+    }
+    buttons.push((
+        ButtonType::Restart.to_string(),
+        ButtonColours {
+            pressed: BUTTON_GREEN_PRESSED.into(),
+            hover: BUTTON_GREEN_HOVER.into(),
+            none: BUTTON_GREEN.into(),
+        },
+    ));
+    buttons.push((
+        ButtonType::Exit.to_string(),
+        ButtonColours {
+            pressed: BUTTON_RED_PRESSED.into(),
+            hover: BUTTON_RED_HOVER.into(),
+            none: BUTTON_RED.into(),
+        },
+    ));
+
+    return new_popup(&"Game Over".to_string(), font, &buttons);
+}
+
+pub fn new_won_popup(font: &Handle<Font>, target_tile: u32) -> PopupComponents {
     return new_popup(
-        &"Game Over".to_string(),
+        &format!("You reached {target_tile}!"),
         font,
         &vec![
             (
-                ButtonType::Restart.to_string(),
+                ButtonType::KeepGoing.to_string(),
                 ButtonColours {
-                    pressed: BUTTON_GREEN_PRESSED.into(),
-                    hover: BUTTON_GREEN_HOVER.into(),
-                    none: BUTTON_GREEN.into(),
+                    pressed: BUTTON_GREEN_PRESSED,
+                    hover: BUTTON_GREEN_HOVER,
+                    none: BUTTON_GREEN,
                 },
             ),
             (
-                ButtonType::Exit.to_string(),
+                ButtonType::NewGame.to_string(),
                 ButtonColours {
-                    pressed: BUTTON_RED_PRESSED.into(),
-                    hover: BUTTON_RED_HOVER.into(),
-                    none: BUTTON_RED.into(),
+                    pressed: BUTTON_GREEN_PRESSED,
+                    hover: BUTTON_GREEN_HOVER,
+                    none: BUTTON_GREEN,
                 },
             ),
         ],
     );
 }
 
-pub fn new_menu_popup(font: &Handle<Font>) -> PopupComponents {
+pub fn new_results_popup(font: &Handle<Font>, text: &String) -> PopupComponents {
     return new_popup(
-        &"2048".to_string(),
+        text,
         font,
         &vec![
             (
-                ButtonType::Continue.to_string(),
+                ButtonType::NewGame.to_string(),
                 ButtonColours {
-                    pressed: BUTTON_GREEN_PRESSED.into(),
-                    hover: BUTTON_GREEN_HOVER.into(),
-                    none: BUTTON_GREEN.into(),
-                },
-            ),
-            (
-                ButtonType::Restart.to_string(),
-                ButtonColours {
-                    pressed: BUTTON_GREEN_PRESSED.into(),
-                    hover: BUTTON_GREEN_HOVER.into(),
-                    none: BUTTON_GREEN.into(),
+                    pressed: BUTTON_GREEN_PRESSED,
+                    hover: BUTTON_GREEN_HOVER,
+                    none: BUTTON_GREEN,
                 },
             ),
             (
                 ButtonType::Exit.to_string(),
                 ButtonColours {
-                    pressed: BUTTON_RED_PRESSED.into(),
-                    hover: BUTTON_RED_HOVER.into(),
-                    none: BUTTON_RED.into(),
+                    pressed: BUTTON_RED_PRESSED,
+                    hover: BUTTON_RED_HOVER,
+                    none: BUTTON_RED,
                 },
             ),
         ],
     );
 }
 
+pub fn new_daily_done_popup(font: &Handle<Font>, result: &DailyResult) -> PopupComponents {
+    return new_notice_popup(
+        font,
+        &format!(
+            "Daily {} already played\nScore: {}",
+            result.date, result.score
+        ),
+    );
+}
+
+/// A message with a single button to carry on.
+pub fn new_notice_popup(font: &Handle<Font>, text: &str) -> PopupComponents {
+    return new_popup(
+        &text.to_string(),
+        font,
+        &vec![(
+            ButtonType::Continue.to_string(),
+            ButtonColours {
+                pressed: BUTTON_GREEN_PRESSED,
+                hover: BUTTON_GREEN_HOVER,
+                none: BUTTON_GREEN,
+            },
+        )],
+    );
+}
+
+pub fn new_menu_popup(
+    font: &Handle<Font>,
+    undo_limit: Option<u32>,
+    solver: SolverKind,
+) -> PopupComponents {
+    let mut buttons = vec![
+        (
+            ButtonType::Continue.to_string(),
+            ButtonColours {
+                pressed: BUTTON_GREEN_PRESSED.into(),
+                hover: BUTTON_GREEN_HOVER.into(),
+                none: BUTTON_GREEN.into(),
+            },
+        ),
+        (
+            ButtonType::Restart.to_string(),
+            ButtonColours {
+                pressed: BUTTON_GREEN_PRESSED.into(),
+                hover: BUTTON_GREEN_HOVER.into(),
+                none: BUTTON_GREEN.into(),
+            },
+        ),
+        (
+            ButtonType::Undo.to_string(),
+            ButtonColours {
+                pressed: BUTTON_GREEN_PRESSED,
+                hover: BUTTON_GREEN_HOVER,
+                none: BUTTON_GREEN,
+            },
+        ),
+        (
+            ButtonType::Redo.to_string(),
+            ButtonColours {
+                pressed: BUTTON_GREEN_PRESSED,
+                hover: BUTTON_GREEN_HOVER,
+                none: BUTTON_GREEN,
+            },
+        ),
+        (
+            ButtonType::UndoLimit(undo_limit).to_string(),
+            ButtonColours {
+                pressed: BUTTON_GREEN_PRESSED,
+                hover: BUTTON_GREEN_HOVER,
+                none: BUTTON_GREEN,
+            },
+        ),
+        (
+            ButtonType::Daily.to_string(),
+            ButtonColours {
+                pressed: BUTTON_GREEN_PRESSED,
+                hover: BUTTON_GREEN_HOVER,
+                none: BUTTON_GREEN,
+            },
+        ),
+        (
+            ButtonType::LoadBoard.to_string(),
+            ButtonColours {
+                pressed: BUTTON_GREEN_PRESSED,
+                hover: BUTTON_GREEN_HOVER,
+                none: BUTTON_GREEN,
+            },
+        ),
+        (
+            ButtonType::Autoplay.to_string(),
+            ButtonColours {
+                pressed: BUTTON_GREEN_PRESSED,
+                hover: BUTTON_GREEN_HOVER,
+                none: BUTTON_GREEN,
+            },
+        ),
+        (
+            ButtonType::Solver(solver).to_string(),
+            ButtonColours {
+                pressed: BUTTON_GREEN_PRESSED,
+                hover: BUTTON_GREEN_HOVER,
+                none: BUTTON_GREEN,
+            },
+        ),
+    ];
+
+    for difficulty in DIFFICULTIES {
+        buttons.push((
+            ButtonType::Difficulty(difficulty).to_string(),
+            ButtonColours {
+                pressed: BUTTON_GREEN_PRESSED,
+                hover: BUTTON_GREEN_HOVER,
+                none: BUTTON_GREEN,
+            },
+        ));
+    }
+
+    buttons.push((
+        ButtonType::Exit.to_string(),
+        ButtonColours {
+            pressed: BUTTON_RED_PRESSED.into(),
+            hover: BUTTON_RED_HOVER.into(),
+            none: BUTTON_RED.into(),
+        },
+    ));
+
+    return new_popup(&"2048".to_string(), font, &buttons);
+}
+
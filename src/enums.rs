@@ -1,23 +1,30 @@
-use bevy::reflect::Reflect;
+use std::fmt;
 
-#[derive(Debug)]
-pub enum MoveDirection {
-    Up,
-    Left,
-    Down,
-    Right,
-}
+use bevy::reflect::Reflect;
+use two_thousand_forty_eight_engine::{
+    ClassicRule, CubeDirection, FibonacciRule, HexDirection, MergeRule, MoveDirection,
+    NegativeRule, Shape, SpawnRules, TriplesRule,
+};
 
-pub const DIRECTIONS: [MoveDirection; 4] = [
-    MoveDirection::Up,
-    MoveDirection::Left,
-    MoveDirection::Down,
-    MoveDirection::Right,
-];
+use crate::constants::{
+    CUBE_SIZE, GRID_COLUMNS, GRID_ROWS, HEX_RADIUS, OBJECTIVES, TARGET_TILE, TIME_LIMITS,
+};
 
 pub enum ButtonType {
     Continue,
     Restart,
+    KeepGoing,
+    NewGame,
+    Undo,
+    Redo,
+    /// Shows the chosen undo limit and switches to the next one.
+    UndoLimit(Option<u32>),
+    /// Shows the active solver and switches to the other one.
+    Solver(SolverKind),
+    Difficulty(Difficulty),
+    Daily,
+    LoadBoard,
+    Autoplay,
     Exit,
 }
 
@@ -26,15 +33,232 @@ impl ButtonType {
         match self {
             ButtonType::Continue => "Continue".to_string(),
             ButtonType::Restart => "Restart".to_string(),
+            ButtonType::KeepGoing => "Keep going".to_string(),
+            ButtonType::NewGame => "New game".to_string(),
+            ButtonType::Undo => "Undo".to_string(),
+            ButtonType::Redo => "Redo".to_string(),
+            ButtonType::UndoLimit(None) => "Undos: unlimited".to_string(),
+            ButtonType::UndoLimit(Some(0)) => "Undos: purist".to_string(),
+            ButtonType::UndoLimit(Some(limit)) => format!("Undos: {limit}"),
+            ButtonType::Solver(kind) => format!("Solver: {kind}"),
+            ButtonType::Difficulty(difficulty) => difficulty.to_string(),
+            ButtonType::Daily => "Daily".to_string(),
+            ButtonType::LoadBoard => "Load board".to_string(),
+            ButtonType::Autoplay => "Autoplay".to_string(),
             ButtonType::Exit => "Exit".to_string(),
         }
     }
 }
 
+#[derive(Reflect, PartialEq, Clone, Copy, Default)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Walls,
+    Fibonacci,
+    Triples,
+    /// Occasional negative tiles that annihilate their positive counterpart.
+    Negative,
+    Hex,
+    Cube,
+    Torus,
+    /// Score as much as possible within the given number of seconds.
+    TimeAttack(u32),
+    /// Reach `target` within `moves` moves, or in as few as possible when
+    /// there is no limit.
+    Objective {
+        target: u32,
+        moves: Option<u32>,
+    },
+}
+
+pub const DIFFICULTIES: [Difficulty; 14] = [
+    Difficulty::Easy,
+    Difficulty::Normal,
+    Difficulty::Hard,
+    Difficulty::Walls,
+    Difficulty::Fibonacci,
+    Difficulty::Triples,
+    Difficulty::Negative,
+    Difficulty::Hex,
+    Difficulty::Cube,
+    Difficulty::Torus,
+    Difficulty::TimeAttack(TIME_LIMITS[0]),
+    Difficulty::TimeAttack(TIME_LIMITS[1]),
+    Difficulty::Objective {
+        target: OBJECTIVES[0].0,
+        moves: OBJECTIVES[0].1,
+    },
+    Difficulty::Objective {
+        target: OBJECTIVES[1].0,
+        moves: OBJECTIVES[1].1,
+    },
+];
+
+impl Difficulty {
+    pub fn spawn_rules(&self) -> SpawnRules {
+        match self {
+            Difficulty::Easy => SpawnRules {
+                values: vec![(2, 3), (4, 1)],
+                ..SpawnRules::classic()
+            },
+            Difficulty::Normal
+            | Difficulty::Hex
+            | Difficulty::Cube
+            | Difficulty::Torus
+            | Difficulty::TimeAttack(_)
+            | Difficulty::Objective { .. } => SpawnRules::classic(),
+            Difficulty::Hard => SpawnRules {
+                values: vec![(2, 6), (4, 3), (8, 1)],
+                edge_only: true,
+                ..SpawnRules::classic()
+            },
+            Difficulty::Walls => SpawnRules {
+                starting_walls: 1,
+                wall_chance: 5,
+                max_walls: 3,
+                ..SpawnRules::classic()
+            },
+            Difficulty::Fibonacci => SpawnRules {
+                values: vec![(1, 9), (2, 1)],
+                ..SpawnRules::classic()
+            },
+            Difficulty::Triples => SpawnRules {
+                values: vec![(3, 9), (9, 1)],
+                ..SpawnRules::classic()
+            },
+            Difficulty::Negative => SpawnRules {
+                values: vec![(2, 16), (4, 2), (-2, 1), (-4, 1)],
+                ..SpawnRules::classic()
+            },
+        }
+    }
+
+    /// Seconds per game, or `None` if the game is not timed.
+    pub fn time_limit(&self) -> Option<u32> {
+        match self {
+            Difficulty::TimeAttack(seconds) => Some(*seconds),
+            _ => None,
+        }
+    }
+
+    pub fn is_objective(&self) -> bool {
+        return matches!(self, Difficulty::Objective { .. });
+    }
+
+    /// Moves allowed per game, or `None` if there is no limit.
+    pub fn move_limit(&self) -> Option<u32> {
+        match self {
+            Difficulty::Objective { moves, .. } => *moves,
+            _ => None,
+        }
+    }
+
+    /// Timed games allow no undos, since they would turn back the board but
+    /// not the clock. Objectives allow none either, so a move count cannot be
+    /// improved by taking moves back, or played on after the objective ends.
+    /// Daily challenges disallow them separately. Other games allow the
+    /// player's `chosen` limit.
+    pub fn undo_limit(&self, chosen: Option<u32>) -> Option<u32> {
+        match self {
+            Difficulty::TimeAttack(_) | Difficulty::Objective { .. } => Some(0),
+            _ => chosen,
+        }
+    }
+
+    pub fn shape(&self) -> Shape {
+        match self {
+            Difficulty::Hex => Shape::Hex { radius: HEX_RADIUS },
+            Difficulty::Cube => Shape::Cube { size: CUBE_SIZE },
+            Difficulty::Torus => Shape::Torus {
+                rows: GRID_ROWS,
+                columns: GRID_COLUMNS,
+            },
+            _ => Shape::Rectangle {
+                rows: GRID_ROWS,
+                columns: GRID_COLUMNS,
+            },
+        }
+    }
+
+    pub fn merge_rule(&self) -> Box<dyn MergeRule> {
+        match self {
+            Difficulty::Fibonacci => Box::new(FibonacciRule),
+            Difficulty::Triples => Box::new(TriplesRule),
+            Difficulty::Negative => Box::new(NegativeRule),
+            _ => Box::new(ClassicRule),
+        }
+    }
+
+    pub fn target_tile(&self) -> u32 {
+        match self {
+            Difficulty::Fibonacci => 2584,
+            Difficulty::Triples => 2187,
+            Difficulty::Objective { target, .. } => *target,
+            _ => TARGET_TILE,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Normal => write!(f, "Normal"),
+            Difficulty::Hard => write!(f, "Hard"),
+            Difficulty::Walls => write!(f, "Walls"),
+            Difficulty::Fibonacci => write!(f, "Fibonacci"),
+            Difficulty::Triples => write!(f, "Triples"),
+            Difficulty::Negative => write!(f, "Negative"),
+            Difficulty::Hex => write!(f, "Hex"),
+            Difficulty::Cube => write!(f, "Cube"),
+            Difficulty::Torus => write!(f, "Torus"),
+            Difficulty::TimeAttack(seconds) => write!(f, "{seconds}s Time Attack"),
+            Difficulty::Objective {
+                target,
+                moves: Some(moves),
+            } => write!(f, "{target} in {moves} moves"),
+            Difficulty::Objective {
+                target,
+                moves: None,
+            } => write!(f, "{target} fastest"),
+        }
+    }
+}
+
+/// A move in one of the directions of any board shape.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Move {
+    Square(MoveDirection),
+    Hex(HexDirection),
+    Cube(CubeDirection),
+}
+
+/// The agents that can give hints and play by themselves.
+#[derive(Reflect, PartialEq, Clone, Copy, Default, Debug)]
+pub enum SolverKind {
+    #[default]
+    Expectimax,
+    /// The network learned by the engine's `ntuple` trainer.
+    NTuple,
+}
+
+impl fmt::Display for SolverKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolverKind::Expectimax => write!(f, "expectimax"),
+            SolverKind::NTuple => write!(f, "n-tuple"),
+        }
+    }
+}
+
 #[derive(Reflect, PartialEq)]
 pub enum GameState {
     Play,
     Menu,
+    Won,
     Gameover,
 }
 
@@ -1,17 +1,21 @@
 #![windows_subsystem = "windows"]
 
+mod board_file;
 mod components;
 mod constants;
+mod daily;
 mod entities;
 mod enums;
 mod resources;
 mod systems;
 
+use board_file::BoardFile;
 use constants::*;
-use enums::GameState;
-use resources::grid::Grid;
-use resources::Game;
+use daily::DailyResults;
+use enums::{Difficulty, GameState};
+use resources::{Autoplay, Countdown, Game, MoveRecords, Solver};
 use systems::*;
+use two_thousand_forty_eight_engine::Grid;
 
 use bevy::prelude::*;
 
@@ -26,7 +30,10 @@ fn main() {
 
         app.add_plugins(WorldInspectorPlugin::new())
             .add_plugins(ResourceInspectorPlugin::<Grid>::default())
-            .add_plugins(ResourceInspectorPlugin::<Game>::default());
+            .add_plugins(ResourceInspectorPlugin::<Game>::default())
+            .add_plugins(ResourceInspectorPlugin::<Countdown>::default())
+            .add_plugins(ResourceInspectorPlugin::<MoveRecords>::default())
+            .add_plugins(ResourceInspectorPlugin::<Autoplay>::default());
     }
 
     #[cfg(feature = "debug-commands")]
@@ -34,24 +41,76 @@ fn main() {
         app.add_systems(Update, debug_commands);
     }
 
-    app.insert_resource(Grid::new(GRID_SIZE))
+    let mut grid = Grid::new(GRID_ROWS, GRID_COLUMNS);
+    grid.undo_limit = UNDO_LIMIT;
+
+    // Boards that do not fill the window, like wrapping ones, sit on the same
+    // colour as the rest of the game.
+    app.insert_resource(ClearColor(BACKGROUND_COLOUR))
+        .insert_resource(grid)
         .insert_resource(Game {
             state: GameState::Play,
+            difficulty: Difficulty::default(),
+            target_tile: TARGET_TILE,
+            keep_going: false,
+            daily: None,
+            hints_used: 0,
+            undo_limit: UNDO_LIMIT,
         })
+        .insert_resource(DailyResults::load())
+        .insert_resource(BoardFile::from_args())
+        .insert_resource(Countdown::default())
+        .insert_resource(MoveRecords::default())
+        .insert_resource(Autoplay::default())
+        .insert_resource(Solver::default())
         .add_event::<GridUpdatedEvent>()
         .add_event::<GameoverEvent>()
+        .add_event::<GameWonEvent>()
+        .add_event::<BoardChangedEvent>()
+        .add_event::<NewGameEvent>()
+        .add_event::<TilesMergedEvent>()
+        .add_event::<ObjectiveEndedEvent>()
+        .add_event::<LoadBoardEvent>()
+        .add_event::<MoveEvent>()
         .add_systems(Startup, setup)
         .add_systems(
             Update,
             (
+                read_move_keys,
                 update_grid,
+                handle_history_keys,
+                rebuild_board,
                 update_scoreboard,
+                restart_countdown,
+                tick_countdown,
+                record_countdown_result,
+                update_countdown_display,
+                update_moves_display,
                 update_box_values,
                 update_box_colours,
+                handle_game_won,
                 handle_game_over,
+                handle_objective_ended,
                 handle_menu,
                 update_button_colours,
                 handle_popup_buttons,
+                handle_daily_button,
+                track_daily,
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                load_board,
+                handle_undo_limit_button,
+                handle_solver_button,
+                request_hint,
+                show_hint,
+                clear_hint,
+                handle_autoplay_button,
+                run_autoplay,
+                play_autoplay_move,
+                stop_autoplay,
             ),
         )
         .run();
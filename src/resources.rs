@@ -1,15 +1,138 @@
-pub mod grid;
+use std::{sync::Arc, time::Duration};
 
 use bevy::{
     ecs::{reflect::ReflectResource, system::Resource},
     reflect::Reflect,
+    time::{Timer, TimerMode},
+    utils::HashMap,
 };
 
-use crate::enums::GameState;
+use crate::constants::AUTOPLAY_MOVES_PER_SECOND;
+use crate::daily::Date;
+use crate::enums::{Difficulty, GameState, SolverKind};
+use two_thousand_forty_eight_engine::ai::{Agent, Expectimax};
 
 #[derive(Resource, Default, Reflect)]
 #[reflect(Resource)]
 pub struct Game {
     pub state: GameState,
+    pub difficulty: Difficulty,
+    pub target_tile: u32,
+    /// Set once the player chooses to continue past the target tile, so the
+    /// victory popup is only shown once per game.
+    pub keep_going: bool,
+    /// The day being played when this is a daily challenge.
+    pub daily: Option<Date>,
+    pub hints_used: u32,
+    /// Undos per game chosen in the menu. Some modes allow fewer.
+    pub undo_limit: Option<u32>,
+}
+
+/// The clock for time attack games.
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct Countdown {
+    /// Seconds per game, or `None` when the game is not timed.
+    pub limit: Option<u32>,
+    pub timer: Timer,
+    /// Best score reached with each time limit this session.
+    pub best_scores: HashMap<u32, u32>,
+}
+
+impl Countdown {
+    pub fn start(&mut self, limit: Option<u32>) {
+        self.limit = limit;
+        self.timer = Timer::from_seconds(limit.unwrap_or(0) as f32, TimerMode::Once);
+    }
+
+    /// Gives back `seconds`, up to the full time limit.
+    pub fn add_seconds(&mut self, seconds: f32) {
+        if self.limit.is_none() || self.timer.finished() {
+            return;
+        }
+        let elapsed = self
+            .timer
+            .elapsed()
+            .saturating_sub(Duration::from_secs_f32(seconds));
+        self.timer.set_elapsed(elapsed);
+    }
+
+    /// Keeps `score` if it is the best yet for the current time limit.
+    pub fn record(&mut self, score: u32) {
+        let Some(limit) = self.limit else {
+            return;
+        };
+        let best = self.best_scores.entry(limit).or_insert(0);
+        *best = score.max(*best);
+    }
+
+    pub fn best(&self, limit: u32) -> u32 {
+        return self.best_scores.get(&limit).copied().unwrap_or(0);
+    }
+}
+
+/// The built-in agent playing the game by itself.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Autoplay {
+    pub enabled: bool,
+    pub moves_per_second: f32,
+    pub timer: Timer,
+}
+
+impl Autoplay {
+    pub fn start(&mut self) {
+        self.enabled = true;
+        let seconds = 1.0 / self.moves_per_second.max(0.1);
+        self.timer = Timer::from_seconds(seconds, TimerMode::Repeating);
+    }
+
+    pub fn stop(&mut self) {
+        self.enabled = false;
+    }
+}
+
+impl Default for Autoplay {
+    fn default() -> Self {
+        return Autoplay {
+            enabled: false,
+            moves_per_second: AUTOPLAY_MOVES_PER_SECOND,
+            timer: Timer::default(),
+        };
+    }
+}
+
+/// The agent behind hints and autoplay. Searches run on other threads, so it
+/// is shared rather than copied.
+#[derive(Resource, Clone)]
+pub struct Solver {
+    pub kind: SolverKind,
+    pub agent: Arc<dyn Agent>,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        return Solver {
+            kind: SolverKind::Expectimax,
+            agent: Arc::new(Expectimax::default()),
+        };
+    }
+}
+
+/// Fewest moves taken to reach each objective's target this session.
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct MoveRecords {
+    pub best_moves: HashMap<u32, u32>,
+}
+
+impl MoveRecords {
+    /// Keeps `moves` if it beats the record for `target`, and returns the
+    /// record.
+    pub fn record(&mut self, target: u32, moves: u32) -> u32 {
+        let best = self.best_moves.entry(target).or_insert(moves);
+        *best = moves.min(*best);
+        return *best;
+    }
 }
 
@@ -1,13 +1,22 @@
+use crate::board_file::BoardFile;
 use crate::components::*;
 use crate::constants::*;
+use crate::daily::{DailyResult, DailyResults, Date};
 use crate::entities::*;
 use crate::enums::*;
-use crate::resources::grid::Grid;
-use crate::resources::Game;
+use crate::resources::{Autoplay, Countdown, Game, MoveRecords, Solver};
+
+use std::sync::Arc;
 
 use bevy::{
     input::{keyboard::KeyboardInput, ButtonState},
     prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool},
+};
+use two_thousand_forty_eight_engine::ai::{classic_board, spawn_odds, NTupleNetwork};
+use two_thousand_forty_eight_engine::{
+    CubeDirection, Direction, Grid, HexDirection, MoveDirection, MoveOutcome, Position, Shape,
+    Tile, HOLE, WALL,
 };
 
 #[derive(Event)]
@@ -16,25 +25,131 @@ pub struct GridUpdatedEvent();
 #[derive(Event)]
 pub struct GameoverEvent();
 
+#[derive(Event)]
+pub struct GameWonEvent();
+
+/// Sent when the grid switches shape and the board has to be rebuilt.
+#[derive(Event)]
+pub struct BoardChangedEvent();
+
+/// Sent whenever a fresh game starts, however it was started.
+#[derive(Event)]
+pub struct NewGameEvent();
+
+/// Values of the tiles produced by merges on the last move.
+#[derive(Event)]
+pub struct TilesMergedEvent(pub Vec<Tile>);
+
+/// Asks `update_grid` to play a move, whether it came from the keyboard or
+/// from autoplay.
+#[derive(Event)]
+pub struct MoveEvent(pub Move);
+
+/// Sent when a move-limited objective is won or lost.
+#[derive(Event)]
+pub struct ObjectiveEndedEvent {
+    pub reached: bool,
+}
+
+/// Asks for the board file to be loaded as the starting position.
+#[derive(Event)]
+pub struct LoadBoardEvent();
+
 pub fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut grid: ResMut<Grid>,
+    board_file: Res<BoardFile>,
     mut grid_updated_event: EventWriter<GridUpdatedEvent>,
+    mut new_game_event: EventWriter<NewGameEvent>,
+    mut load_board_event: EventWriter<LoadBoardEvent>,
 ) {
-    grid.add_boxes(2);
+    grid.add_starting_boxes();
 
     commands.spawn(Camera2dBundle::default());
 
-    let board = commands.spawn(new_board()).insert(Name::new("Board")).id();
+    spawn_board(&mut commands, &asset_server.load(FONT_PATH), &grid);
 
-    for i in 0..GRID_SIZE {
-        for j in 0..GRID_SIZE {
-            let tile_components = new_tile(
-                grid.state[i][j],
-                TilePosition { i, j },
-                &asset_server.load(FONT_PATH),
-            );
+    let _score = commands
+        .spawn(new_scoreboard(0, &asset_server.load(FONT_PATH)))
+        .insert(Name::new("Score"))
+        .id();
+
+    let _countdown = commands
+        .spawn(new_countdown_display(&asset_server.load(FONT_PATH)))
+        .insert(Name::new("Countdown"))
+        .id();
+
+    let _moves = commands
+        .spawn(new_moves_display(&asset_server.load(FONT_PATH)))
+        .insert(Name::new("Moves"))
+        .id();
+
+    grid_updated_event.send(GridUpdatedEvent());
+    new_game_event.send(NewGameEvent());
+    if board_file.load_on_start {
+        load_board_event.send(LoadBoardEvent());
+    }
+}
+
+fn spawn_board(commands: &mut Commands, font: &Handle<Font>, grid: &Grid) -> Entity {
+    let (board, parents) = match grid.shape() {
+        Shape::Rectangle { .. } | Shape::Torus { .. } => {
+            let board = commands
+                .spawn(new_board(grid.rows(), grid.columns(), grid.shape().wraps()))
+                .insert((Board, Name::new("Board")))
+                .id();
+            (board, vec![board])
+        }
+        Shape::Hex { radius } => {
+            let hex_board_components = new_hex_board(radius);
+            let board = commands
+                .spawn(hex_board_components.container)
+                .insert((Board, Name::new("Board")))
+                .id();
+            let cells = commands
+                .spawn(hex_board_components.cells)
+                .insert(Name::new("Board Cells"))
+                .id();
+            commands.entity(board).push_children(&[cells]);
+            (board, vec![cells])
+        }
+        Shape::Cube { size } => {
+            let cube_board_components = new_cube_board(size);
+            let board = commands
+                .spawn(cube_board_components.container)
+                .insert((Board, Name::new("Board")))
+                .id();
+            let mut layers = vec![];
+            for (k, layer) in cube_board_components.layers.into_iter().enumerate() {
+                let layer = commands
+                    .spawn(layer)
+                    .insert(Name::new(format!("Layer {k}")))
+                    .id();
+                commands.entity(board).push_children(&[layer]);
+                layers.push(layer);
+            }
+            (board, layers)
+        }
+    };
+
+    for i in 0..grid.rows() {
+        for j in 0..grid.columns() {
+            if grid.state[i][j] == HOLE {
+                continue;
+            }
+
+            let position = Position { i, j };
+            let tile_components = match (grid.shape(), grid.shape().axial(position)) {
+                (Shape::Hex { radius }, Some(axial)) => {
+                    new_hex_tile(grid.state[i][j], TilePosition { i, j }, axial, radius, font)
+                }
+                (Shape::Cube { .. }, _) => {
+                    new_cube_tile(grid.state[i][j], TilePosition { i, j }, font)
+                }
+                _ => new_tile(grid.state[i][j], TilePosition { i, j }, font),
+            };
+            let parent = grid.shape().layer(position).map_or(0, |(layer, _)| layer);
 
             let tile_container = commands
                 .spawn(tile_components.container)
@@ -50,59 +165,224 @@ pub fn setup(
                 .id();
 
             commands.entity(tile_container).push_children(&[tile_text]);
-            commands.entity(board).push_children(&[tile_container]);
+            commands
+                .entity(parents[parent])
+                .push_children(&[tile_container]);
         }
     }
 
-    let _score = commands
-        .spawn(new_scoreboard(0, &asset_server.load(FONT_PATH)))
-        .insert(Name::new("Score"))
+    let hint_arrow_components = new_hint_arrow(font);
+    let hint_arrow = commands
+        .spawn(hint_arrow_components.container)
+        .insert(Name::new("Hint"))
         .id();
+    let hint_text = commands
+        .spawn(hint_arrow_components.text)
+        .insert(Name::new("Hint Arrow"))
+        .id();
+    commands.entity(hint_arrow).push_children(&[hint_text]);
+    commands.entity(board).push_children(&[hint_arrow]);
+
+    return board;
+}
+
+pub fn rebuild_board(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    grid: Res<Grid>,
+    mut board_query: Query<Entity, With<Board>>,
+    mut board_changed_event: EventReader<BoardChangedEvent>,
+    mut grid_updated_event: EventWriter<GridUpdatedEvent>,
+) {
+    if board_changed_event.read().count() == 0 {
+        return;
+    }
+
+    for entity in &mut board_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_board(&mut commands, &asset_server.load(FONT_PATH), &grid);
 
     grid_updated_event.send(GridUpdatedEvent());
 }
 
+/// Values of the tiles merged by a move, or `None` if the move changed nothing.
+fn merged_values<D: Direction>(outcome: MoveOutcome<D>) -> Option<Vec<Tile>> {
+    if !outcome.has_changed() {
+        return None;
+    }
+    return Some(outcome.merges.iter().map(|merge| merge.value).collect());
+}
+
+fn move_direction(key_code: KeyCode) -> Option<MoveDirection> {
+    match key_code {
+        KeyCode::W | KeyCode::Up => Some(MoveDirection::Up),
+        KeyCode::A | KeyCode::Left => Some(MoveDirection::Left),
+        KeyCode::S | KeyCode::Down => Some(MoveDirection::Down),
+        KeyCode::D | KeyCode::Right => Some(MoveDirection::Right),
+        _ => None,
+    }
+}
+
+/// Q/W/E sit above A/S/D the same way the three northern hex directions sit
+/// above the three southern ones.
+fn hex_direction(key_code: KeyCode) -> Option<HexDirection> {
+    match key_code {
+        KeyCode::W | KeyCode::Up => Some(HexDirection::North),
+        KeyCode::E => Some(HexDirection::NorthEast),
+        KeyCode::D => Some(HexDirection::SouthEast),
+        KeyCode::S | KeyCode::Down => Some(HexDirection::South),
+        KeyCode::A => Some(HexDirection::SouthWest),
+        KeyCode::Q => Some(HexDirection::NorthWest),
+        _ => None,
+    }
+}
+
+/// The square board keys, plus Q and E to slide towards the front and back
+/// layers.
+fn cube_direction(key_code: KeyCode) -> Option<CubeDirection> {
+    match key_code {
+        KeyCode::W | KeyCode::Up => Some(CubeDirection::Up),
+        KeyCode::A | KeyCode::Left => Some(CubeDirection::Left),
+        KeyCode::S | KeyCode::Down => Some(CubeDirection::Down),
+        KeyCode::D | KeyCode::Right => Some(CubeDirection::Right),
+        KeyCode::Q => Some(CubeDirection::Forward),
+        KeyCode::E => Some(CubeDirection::Back),
+        _ => None,
+    }
+}
+
+/// Turns key presses into moves for the current board shape.
+pub fn read_move_keys(
+    grid: Res<Grid>,
+    mut key_evr: EventReader<KeyboardInput>,
+    mut move_event: EventWriter<MoveEvent>,
+) {
+    let mut pressed: Option<KeyCode> = None;
+
+    for event in key_evr.read() {
+        if let (ButtonState::Pressed, Some(key_code)) = (event.state, event.key_code) {
+            pressed = Some(key_code);
+        }
+    }
+
+    let Some(key_code) = pressed else {
+        return;
+    };
+
+    let direction = match grid.shape() {
+        Shape::Rectangle { .. } | Shape::Torus { .. } => move_direction(key_code).map(Move::Square),
+        Shape::Hex { .. } => hex_direction(key_code).map(Move::Hex),
+        Shape::Cube { .. } => cube_direction(key_code).map(Move::Cube),
+    };
+    if let Some(direction) = direction {
+        move_event.send(MoveEvent(direction));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn update_grid(
     mut grid: ResMut<Grid>,
     game: Res<Game>,
-    mut key_evr: EventReader<KeyboardInput>,
+    mut move_event: EventReader<MoveEvent>,
     mut grid_updated_event: EventWriter<GridUpdatedEvent>,
     mut gameover_event: EventWriter<GameoverEvent>,
+    mut game_won_event: EventWriter<GameWonEvent>,
+    mut tiles_merged_event: EventWriter<TilesMergedEvent>,
+    mut objective_ended_event: EventWriter<ObjectiveEndedEvent>,
 ) {
+    let Some(MoveEvent(direction)) = move_event.read().last() else {
+        return;
+    };
     if grid.deadlocked || game.state != GameState::Play {
         return;
     }
 
-    let mut direction: Option<MoveDirection> = None;
+    let merges = match *direction {
+        Move::Square(direction) => merged_values(grid.update(direction)),
+        Move::Hex(direction) => merged_values(grid.update(direction)),
+        Move::Cube(direction) => merged_values(grid.update(direction)),
+    };
 
-    for event in key_evr.read() {
-        match event.state {
-            ButtonState::Pressed => match event.key_code {
-                Some(KeyCode::W) | Some(KeyCode::Up) => {
-                    direction = Some(MoveDirection::Up);
-                }
-                Some(KeyCode::D) | Some(KeyCode::Right) => {
-                    direction = Some(MoveDirection::Right);
-                }
-                Some(KeyCode::S) | Some(KeyCode::Down) => {
-                    direction = Some(MoveDirection::Down);
-                }
-                Some(KeyCode::A) | Some(KeyCode::Left) => {
-                    direction = Some(MoveDirection::Left);
-                }
-                _ => (),
-            },
-            _ => (),
+    if let Some(merges) = merges {
+        grid_updated_event.send(GridUpdatedEvent());
+        if !merges.is_empty() {
+            tiles_merged_event.send(TilesMergedEvent(merges));
+        }
+        // Objectives end on reaching the target or running out of moves,
+        // whichever comes first. Otherwise a deadlock on the winning move is
+        // reported once the player chooses to keep going.
+        if game.difficulty.is_objective() {
+            let reached = grid.max_tile() >= game.target_tile;
+            let out_of_moves = game
+                .difficulty
+                .move_limit()
+                .is_some_and(|limit| grid.moves() >= limit);
+            if reached || out_of_moves || grid.deadlocked {
+                objective_ended_event.send(ObjectiveEndedEvent { reached });
+            }
+        } else if !game.keep_going && grid.max_tile() >= game.target_tile {
+            game_won_event.send(GameWonEvent());
+        } else if grid.deadlocked {
+            gameover_event.send(GameoverEvent());
         }
     }
+}
 
-    if let Some(direction) = direction {
-        let has_changed = grid.update(direction);
-        if has_changed {
-            grid_updated_event.send(GridUpdatedEvent());
-            if grid.deadlocked {
-                gameover_event.send(GameoverEvent());
-            }
+fn spawn_popup(
+    commands: &mut Commands,
+    popup_components: PopupComponents,
+    name: &str,
+) -> Entity {
+    let popup = commands
+        .spawn(popup_components.container)
+        .insert(Name::new(format!("{name} Popup")))
+        .id();
+    let popup_text = commands
+        .spawn(popup_components.text)
+        .insert(Name::new(format!("{name} Text")))
+        .id();
+    let button_list = commands.spawn(popup_components.button_list).id();
+
+    commands
+        .entity(popup)
+        .push_children(&[popup_text, button_list]);
+
+    for ButtonComponents { container, text } in popup_components.buttons.into_iter() {
+        let button_container = commands.spawn(container).id();
+        let button_text = commands.spawn(text).id();
+
+        commands
+            .entity(button_container)
+            .push_children(&[button_text]);
+        commands
+            .entity(button_list)
+            .push_children(&[button_container]);
+    }
+
+    return popup;
+}
+
+pub fn handle_history_keys(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut grid: ResMut<Grid>,
+    mut game: ResMut<Game>,
+    mut popup_query: Query<Entity, With<Popup>>,
+    mut grid_updated_event: EventWriter<GridUpdatedEvent>,
+    mut gameover_event: EventWriter<GameoverEvent>,
+) {
+    let can_undo = game.state == GameState::Play || game.state == GameState::Gameover;
+    if keys.just_pressed(KeyCode::Z) && can_undo && grid.undo() {
+        grid_updated_event.send(GridUpdatedEvent());
+        for entity in &mut popup_query {
+            commands.entity(entity).despawn_recursive()
+        }
+        game.state = GameState::Play;
+    } else if keys.just_pressed(KeyCode::Y) && game.state == GameState::Play && grid.redo() {
+        grid_updated_event.send(GridUpdatedEvent());
+        if grid.deadlocked {
+            gameover_event.send(GameoverEvent());
         }
     }
 }
@@ -110,35 +390,69 @@ pub fn update_grid(
 pub fn handle_game_over(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    grid: Res<Grid>,
     mut game: ResMut<Game>,
     mut gameover_event: EventReader<GameoverEvent>,
 ) {
     for _ in gameover_event.read() {
-        let gameover_popup_components = new_gameover_popup(&asset_server.load(FONT_PATH));
-        let game_over_popup = commands
-            .spawn(gameover_popup_components.container)
-            .insert(Name::new("Game Over Popup"))
-            .id();
-        let game_over_text = commands
-            .spawn(gameover_popup_components.text)
-            .insert(Name::new("Game Over Text"))
-            .id();
+        spawn_popup(
+            &mut commands,
+            new_gameover_popup(&asset_server.load(FONT_PATH), grid.can_undo()),
+            "Game Over",
+        );
 
-        commands
-            .entity(game_over_popup)
-            .push_children(&[game_over_text]);
+        game.state = GameState::Gameover;
+    }
+}
+
+pub fn handle_game_won(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut game: ResMut<Game>,
+    mut game_won_event: EventReader<GameWonEvent>,
+) {
+    for _ in game_won_event.read() {
+        spawn_popup(
+            &mut commands,
+            new_won_popup(&asset_server.load(FONT_PATH), game.target_tile),
+            "Won",
+        );
 
-        for ButtonComponents { container, text } in gameover_popup_components.buttons.into_iter() {
-            let button_container = commands.spawn(container).id();
-            let button_text = commands.spawn(text).id();
+        game.state = GameState::Won;
+    }
+}
 
-            commands
-                .entity(button_container)
-                .push_children(&[button_text]);
-            commands
-                .entity(game_over_popup)
-                .push_children(&[button_container]);
-        }
+pub fn handle_objective_ended(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut grid: ResMut<Grid>,
+    mut game: ResMut<Game>,
+    mut records: ResMut<MoveRecords>,
+    mut objective_ended_event: EventReader<ObjectiveEndedEvent>,
+) {
+    for ObjectiveEndedEvent { reached } in objective_ended_event.read() {
+        let text = if *reached {
+            let best = records.record(game.target_tile, grid.moves());
+            format!(
+                "Reached {} in {} moves\nBest: {best}",
+                game.target_tile,
+                grid.moves()
+            )
+        } else {
+            format!(
+                "{} not reached\n{} moves used",
+                game.target_tile,
+                grid.moves()
+            )
+        };
+
+        // Like a full board, the objective allows no further moves.
+        grid.deadlocked = true;
+        spawn_popup(
+            &mut commands,
+            new_results_popup(&asset_server.load(FONT_PATH), &text),
+            "Results",
+        );
 
         game.state = GameState::Gameover;
     }
@@ -148,10 +462,12 @@ pub fn handle_menu(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut game: ResMut<Game>,
+    solver: Res<Solver>,
     keys: Res<Input<KeyCode>>,
     mut menu_query: Query<Entity, With<Menu>>,
 ) {
-    if keys.just_released(KeyCode::Escape) {
+    // The victory popup has to be answered with Keep going or New game first.
+    if keys.just_released(KeyCode::Escape) && game.state != GameState::Won {
         if menu_query.iter().count() > 0 {
             for entity in &mut menu_query {
                 commands.entity(entity).despawn_recursive();
@@ -160,30 +476,12 @@ pub fn handle_menu(
             return;
         }
 
-        let menu_popup_components = new_menu_popup(&asset_server.load(FONT_PATH));
-        let menu_popup = commands
-            .spawn(menu_popup_components.container)
-            .insert(Name::new("Menu Popup"))
-            .insert(Menu)
-            .id();
-        let menu_text = commands
-            .spawn(menu_popup_components.text)
-            .insert(Name::new("Menu text"))
-            .id();
-
-        commands.entity(menu_popup).push_children(&[menu_text]);
-
-        for ButtonComponents { container, text } in menu_popup_components.buttons.into_iter() {
-            let button_container = commands.spawn(container).id();
-            let button_text = commands.spawn(text).id();
-
-            commands
-                .entity(button_container)
-                .push_children(&[button_text]);
-            commands
-                .entity(menu_popup)
-                .push_children(&[button_container]);
-        }
+        let menu_popup = spawn_popup(
+            &mut commands,
+            new_menu_popup(&asset_server.load(FONT_PATH), game.undo_limit, solver.kind),
+            "Menu",
+        );
+        commands.entity(menu_popup).insert(Menu);
 
         game.state = GameState::Menu;
     }
@@ -201,10 +499,117 @@ pub fn update_scoreboard(
     }
 }
 
+pub fn restart_countdown(
+    game: Res<Game>,
+    mut countdown: ResMut<Countdown>,
+    mut new_game_event: EventReader<NewGameEvent>,
+) {
+    if new_game_event.read().count() > 0 {
+        countdown.start(game.difficulty.time_limit());
+    }
+}
+
+/// Runs the clock only while the game is being played, so it stops under the
+/// menu and any popup.
+pub fn tick_countdown(
+    time: Res<Time>,
+    game: Res<Game>,
+    mut grid: ResMut<Grid>,
+    mut countdown: ResMut<Countdown>,
+    mut tiles_merged_event: EventReader<TilesMergedEvent>,
+    mut gameover_event: EventWriter<GameoverEvent>,
+) {
+    for TilesMergedEvent(merges) in tiles_merged_event.read() {
+        let bonus = merges
+            .iter()
+            .filter(|value| **value >= TIME_BONUS_TILE)
+            .count();
+        countdown.add_seconds(bonus as f32 * TIME_BONUS_SECONDS);
+    }
+
+    if countdown.limit.is_none() || game.state != GameState::Play {
+        return;
+    }
+
+    countdown.timer.tick(time.delta());
+    if countdown.timer.just_finished() {
+        // No more moves are allowed once time is up, just as on a full board.
+        grid.deadlocked = true;
+        gameover_event.send(GameoverEvent());
+    }
+}
+
+pub fn record_countdown_result(
+    grid: Res<Grid>,
+    mut countdown: ResMut<Countdown>,
+    mut gameover_event: EventReader<GameoverEvent>,
+) {
+    if gameover_event.read().count() > 0 {
+        countdown.record(grid.score);
+    }
+}
+
+pub fn update_countdown_display(
+    countdown: Res<Countdown>,
+    mut query: Query<&mut Text, With<CountdownDisplay>>,
+) {
+    let text = match countdown.limit {
+        Some(limit) => {
+            let remaining = countdown.timer.remaining_secs().ceil() as u32;
+            format!(
+                "{}:{:02}  Best: {}",
+                remaining / 60,
+                remaining % 60,
+                countdown.best(limit)
+            )
+        }
+        None => String::new(),
+    };
+    for mut display in &mut query {
+        if display.sections[0].value != text {
+            display.sections[0].value = text.clone();
+        }
+    }
+}
+
+pub fn update_moves_display(
+    grid: Res<Grid>,
+    game: Res<Game>,
+    mut query: Query<&mut Text, With<MovesDisplay>>,
+) {
+    let mut text = if !game.difficulty.is_objective() {
+        String::new()
+    } else if let Some(limit) = game.difficulty.move_limit() {
+        format!("Moves: {} / {limit}", grid.moves())
+    } else {
+        format!("Moves: {}", grid.moves())
+    };
+    if let Some(remaining) = grid
+        .undos_remaining()
+        .filter(|_| grid.undo_limit != Some(0))
+    {
+        if !text.is_empty() {
+            text.push_str("  ");
+        }
+        text.push_str(&format!("Undos left: {remaining}"));
+    }
+    if game.hints_used > 0 {
+        if !text.is_empty() {
+            text.push_str("  ");
+        }
+        text.push_str(&format!("Hints: {}", game.hints_used));
+    }
+    for mut display in &mut query {
+        if display.sections[0].value != text {
+            display.sections[0].value = text.clone();
+        }
+    }
+}
+
 pub fn update_box_values(grid: Res<Grid>, mut query: Query<(&mut Text, &TilePosition)>) {
     for (mut text, box_pos) in &mut query {
         let value = grid.state[box_pos.i][box_pos.j];
-        if value != 0 {
+        if value != 0 && value != WALL {
             text.sections[0].value = value.to_string();
         } else {
             text.sections[0].value = "".to_string();
@@ -214,32 +619,37 @@ pub fn update_box_values(grid: Res<Grid>, mut query: Query<(&mut Text, &TilePosi
 
 pub fn update_box_colours(
     grid: Res<Grid>,
+    game: Res<Game>,
     mut query: Query<(&mut BackgroundColor, &TilePosition), With<TileColour>>,
     mut grid_updated_event: EventReader<GridUpdatedEvent>,
 ) {
+    // Colours follow the tile's place in the active rule's sequence, so 2, 4,
+    // 8... in classic and 1, 2, 3, 5... in Fibonacci share the same ramp.
+    // Negative tiles get a ramp of their own.
+    let ramp: &[Color] = match game.difficulty {
+        Difficulty::Triples => &TRIPLES_TILE_COLOURS,
+        _ => &TILE_COLOURS,
+    };
+
     for _ in grid_updated_event.read() {
         for (mut colour, box_pos) in &mut query {
             let value = grid.state[box_pos.i][box_pos.j];
-            if value > 16384 {
-                *colour = Color::rgb(160.0 / 255.0, 0.0 / 255.0, 0.0 / 255.0).into();
-                return;
+            if value == WALL {
+                *colour = WALL_COLOUR.into();
+                continue;
             }
-            match value {
-                2 => *colour = Color::rgb(209.0 / 255.0, 205.0 / 255.0, 100.0 / 255.0).into(),
-                4 => *colour = Color::rgb(209.0 / 255.0, 160.0 / 255.0, 69.0 / 255.0).into(),
-                8 => *colour = Color::rgb(209.0 / 255.0, 93.0 / 255.0, 54.0 / 255.0).into(),
-                16 => *colour = Color::rgb(209.0 / 255.0, 54.0 / 255.0, 54.0 / 255.0).into(),
-                32 => *colour = Color::rgb(209.0 / 255.0, 65.0 / 255.0, 144.0 / 255.0).into(),
-                64 => *colour = Color::rgb(162.0 / 255.0, 65.0 / 255.0, 191.0 / 255.0).into(),
-                128 => *colour = Color::rgb(100.0 / 255.0, 57.0 / 255.0, 191.0 / 255.0).into(),
-                256 => *colour = Color::rgb(57.0 / 255.0, 60.0 / 255.0, 191.0 / 255.0).into(),
-                512 => *colour = Color::rgb(86.0 / 255.0, 127.0 / 255.0, 270.0 / 255.0).into(),
-                1024 => *colour = Color::rgb(46.0 / 255.0, 164.0 / 255.0, 232.0 / 255.0).into(),
-                2048 => *colour = Color::rgb(41.0 / 255.0, 212.0 / 255.0, 184.0 / 255.0).into(),
-                4096 => *colour = Color::rgb(47.0 / 255.0, 194.0 / 255.0, 81.0 / 255.0).into(),
-                8192 => *colour = Color::rgb(66.0 / 255.0, 143.0 / 255.0, 30.0 / 255.0).into(),
-                16384 => *colour = Color::rgb(182.0 / 255.0, 227.0 / 255.0, 68.0 / 255.0).into(),
-                _ => *colour = Color::rgb(25.0 / 255.0, 25.0 / 255.0, 25.0 / 255.0).into(),
+            let ramp = if value < 0 {
+                &NEGATIVE_TILE_COLOURS
+            } else {
+                ramp
+            };
+            let rank = grid.merge_rule.rank(value) as usize;
+            if rank == 0 {
+                *colour = EMPTY_TILE_COLOUR.into();
+            } else if rank > ramp.len() {
+                *colour = OVERFLOW_TILE_COLOUR.into();
+            } else {
+                *colour = ramp[rank - 1].into();
             }
         }
     }
@@ -263,6 +673,7 @@ pub fn update_button_colours(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn handle_popup_buttons(
     mut commands: Commands,
     mut grid: ResMut<Grid>,
@@ -270,6 +681,10 @@ pub fn handle_popup_buttons(
     mut button_query: Query<(&Interaction, &Name), (Changed<Interaction>, With<Button>)>,
     mut popup_query: Query<Entity, With<Popup>>,
     mut grid_updated_event: EventWriter<GridUpdatedEvent>,
+    mut gameover_event: EventWriter<GameoverEvent>,
+    mut board_changed_event: EventWriter<BoardChangedEvent>,
+    mut new_game_event: EventWriter<NewGameEvent>,
+    mut load_board_event: EventWriter<LoadBoardEvent>,
 ) {
     for (interaction, name) in &mut button_query {
         if name.to_string() == ButtonType::Continue.to_string() {
@@ -282,11 +697,69 @@ pub fn handle_popup_buttons(
                 }
                 _ => (),
             }
-        } else if name.to_string() == ButtonType::Restart.to_string() {
+        } else if name.to_string() == ButtonType::KeepGoing.to_string() {
+            match *interaction {
+                Interaction::Pressed => {
+                    for entity in &mut popup_query {
+                        commands.entity(entity).despawn_recursive()
+                    }
+                    game.keep_going = true;
+                    game.state = GameState::Play;
+                    if grid.deadlocked {
+                        gameover_event.send(GameoverEvent());
+                    }
+                }
+                _ => (),
+            }
+        } else if name.to_string() == ButtonType::Undo.to_string() {
+            if *interaction == Interaction::Pressed && grid.undo() {
+                grid_updated_event.send(GridUpdatedEvent());
+                for entity in &mut popup_query {
+                    commands.entity(entity).despawn_recursive()
+                }
+                game.state = GameState::Play;
+            }
+        } else if name.to_string() == ButtonType::Redo.to_string() {
+            if *interaction == Interaction::Pressed && grid.redo() {
+                grid_updated_event.send(GridUpdatedEvent());
+                for entity in &mut popup_query {
+                    commands.entity(entity).despawn_recursive()
+                }
+                game.state = GameState::Play;
+                if grid.deadlocked {
+                    gameover_event.send(GameoverEvent());
+                }
+            }
+        } else if name.to_string() == ButtonType::Restart.to_string()
+            || name.to_string() == ButtonType::NewGame.to_string()
+        {
+            match *interaction {
+                Interaction::Pressed => {
+                    grid.undo_limit = game.difficulty.undo_limit(game.undo_limit);
+                    grid.reset();
+                    game.keep_going = false;
+                    game.daily = None;
+                    grid_updated_event.send(GridUpdatedEvent());
+                    new_game_event.send(NewGameEvent());
+                    for entity in &mut popup_query {
+                        commands.entity(entity).despawn_recursive()
+                    }
+                    game.state = GameState::Play;
+                }
+                _ => (),
+            }
+        } else if let Some(difficulty) = DIFFICULTIES
+            .into_iter()
+            .find(|difficulty| name.to_string() == ButtonType::Difficulty(*difficulty).to_string())
+        {
             match *interaction {
                 Interaction::Pressed => {
+                    if set_difficulty(&mut grid, &mut game, difficulty) {
+                        board_changed_event.send(BoardChangedEvent());
+                    }
                     grid.reset();
                     grid_updated_event.send(GridUpdatedEvent());
+                    new_game_event.send(NewGameEvent());
                     for entity in &mut popup_query {
                         commands.entity(entity).despawn_recursive()
                     }
@@ -294,6 +767,10 @@ pub fn handle_popup_buttons(
                 }
                 _ => (),
             }
+        } else if name.to_string() == ButtonType::LoadBoard.to_string() {
+            if *interaction == Interaction::Pressed {
+                load_board_event.send(LoadBoardEvent());
+            }
         } else if name.to_string() == ButtonType::Exit.to_string() {
             match *interaction {
                 Interaction::Pressed => {
@@ -305,21 +782,471 @@ pub fn handle_popup_buttons(
     }
 }
 
+/// Switches to the next undo limit from the menu. The new limit applies
+/// straight away, except in daily challenges, which never allow undos.
+pub fn handle_undo_limit_button(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut grid: ResMut<Grid>,
+    mut game: ResMut<Game>,
+    solver: Res<Solver>,
+    button_query: Query<(&Interaction, &Name), (Changed<Interaction>, With<Button>)>,
+    popup_query: Query<Entity, With<Popup>>,
+) {
+    for (interaction, name) in &button_query {
+        if *interaction != Interaction::Pressed
+            || name.to_string() != ButtonType::UndoLimit(game.undo_limit).to_string()
+        {
+            continue;
+        }
+
+        let next = UNDO_LIMITS
+            .iter()
+            .position(|limit| *limit == game.undo_limit)
+            .map_or(0, |index| (index + 1) % UNDO_LIMITS.len());
+        game.undo_limit = UNDO_LIMITS[next];
+        if game.daily.is_none() {
+            grid.undo_limit = game.difficulty.undo_limit(game.undo_limit);
+        }
+
+        for entity in &popup_query {
+            commands.entity(entity).despawn_recursive()
+        }
+        let popup = spawn_popup(
+            &mut commands,
+            new_menu_popup(&asset_server.load(FONT_PATH), game.undo_limit, solver.kind),
+            "Menu",
+        );
+        commands.entity(popup).insert(Menu);
+    }
+}
+
+/// Switches the grid's rules to `difficulty` without resetting it. Returns
+/// whether the board shape changed.
+fn set_difficulty(grid: &mut Grid, game: &mut Game, difficulty: Difficulty) -> bool {
+    game.difficulty = difficulty;
+    game.target_tile = difficulty.target_tile();
+    game.keep_going = false;
+    game.daily = None;
+    grid.spawn_rules = difficulty.spawn_rules();
+    grid.merge_rule = difficulty.merge_rule();
+    grid.undo_limit = difficulty.undo_limit(game.undo_limit);
+    if grid.shape() != difficulty.shape() {
+        grid.set_shape(difficulty.shape());
+        return true;
+    }
+    return false;
+}
+
+/// Starts today's daily game, or shows today's result if it was already
+/// played. The board is seeded from the date and undos are off, so everyone
+/// gets the same single attempt.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_daily_button(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut grid: ResMut<Grid>,
+    mut game: ResMut<Game>,
+    mut daily_results: ResMut<DailyResults>,
+    button_query: Query<(&Interaction, &Name), (Changed<Interaction>, With<Button>)>,
+    popup_query: Query<Entity, With<Popup>>,
+    mut grid_updated_event: EventWriter<GridUpdatedEvent>,
+    mut board_changed_event: EventWriter<BoardChangedEvent>,
+    mut new_game_event: EventWriter<NewGameEvent>,
+) {
+    for (interaction, name) in &button_query {
+        if *interaction != Interaction::Pressed || name.to_string() != ButtonType::Daily.to_string()
+        {
+            continue;
+        }
+        for entity in &popup_query {
+            commands.entity(entity).despawn_recursive()
+        }
+
+        let today = Date::today();
+        if let Some(result) = daily_results.get(today) {
+            let popup = spawn_popup(
+                &mut commands,
+                new_daily_done_popup(&asset_server.load(FONT_PATH), result),
+                "Daily",
+            );
+            commands.entity(popup).insert(Menu);
+            game.state = GameState::Menu;
+            continue;
+        }
+
+        if set_difficulty(&mut grid, &mut game, Difficulty::Normal) {
+            board_changed_event.send(BoardChangedEvent());
+        }
+        grid.undo_limit = Some(0);
+        grid.reset_with_seed(today.seed());
+        game.daily = Some(today);
+        daily_results.record(DailyResult {
+            date: today,
+            score: 0,
+            max_tile: grid.max_tile(),
+            moves: 0,
+            finished: false,
+        });
+        if let Err(error) = daily_results.save() {
+            warn!("Could not save daily results: {}", error);
+        }
+        grid_updated_event.send(GridUpdatedEvent());
+        new_game_event.send(NewGameEvent());
+        game.state = GameState::Play;
+    }
+}
+
+/// Saves the daily attempt after every move, so quitting midway still uses
+/// up the day's attempt.
+pub fn track_daily(
+    grid: Res<Grid>,
+    game: Res<Game>,
+    mut daily_results: ResMut<DailyResults>,
+    mut grid_updated_event: EventReader<GridUpdatedEvent>,
+) {
+    if grid_updated_event.read().count() == 0 {
+        return;
+    }
+    let Some(date) = game.daily else {
+        return;
+    };
+    daily_results.record(DailyResult {
+        date,
+        score: grid.score,
+        max_tile: grid.max_tile(),
+        moves: grid.moves(),
+        finished: grid.deadlocked,
+    });
+    if let Err(error) = daily_results.save() {
+        warn!("Could not save daily results: {}", error);
+    }
+}
+
+/// Starts a game from the board file. Board files hold classic tiles on the
+/// standard board, so the difficulty switches to Normal. If the file cannot
+/// be used, a popup explains why.
+#[allow(clippy::too_many_arguments)]
+pub fn load_board(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut grid: ResMut<Grid>,
+    mut game: ResMut<Game>,
+    board_file: Res<BoardFile>,
+    popup_query: Query<Entity, With<Popup>>,
+    mut load_board_event: EventReader<LoadBoardEvent>,
+    mut grid_updated_event: EventWriter<GridUpdatedEvent>,
+    mut gameover_event: EventWriter<GameoverEvent>,
+    mut board_changed_event: EventWriter<BoardChangedEvent>,
+    mut new_game_event: EventWriter<NewGameEvent>,
+) {
+    if load_board_event.read().count() == 0 {
+        return;
+    }
+    for entity in &popup_query {
+        commands.entity(entity).despawn_recursive()
+    }
+
+    let (rows, columns) = Difficulty::Normal.shape().dimensions();
+    match board_file.read(rows, columns) {
+        Ok(state) => {
+            if set_difficulty(&mut grid, &mut game, Difficulty::Normal) {
+                board_changed_event.send(BoardChangedEvent());
+            }
+            grid.reset_with_state(state);
+            grid_updated_event.send(GridUpdatedEvent());
+            new_game_event.send(NewGameEvent());
+            game.state = GameState::Play;
+            if grid.deadlocked {
+                gameover_event.send(GameoverEvent());
+            }
+        }
+        Err(error) => {
+            warn!("{}", error);
+            let popup = spawn_popup(
+                &mut commands,
+                new_notice_popup(
+                    &asset_server.load(FONT_PATH),
+                    &format!("Could not load board\n{error}"),
+                ),
+                "Load Error",
+            );
+            commands.entity(popup).insert(Menu);
+            game.state = GameState::Menu;
+        }
+    }
+}
+
+/// Switches hints and autoplay between expectimax and the learned network in
+/// [`NTUPLE_FILE`]. The file is only read when the network is picked, so no
+/// weights are used without the player asking for them.
+pub fn handle_solver_button(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game: Res<Game>,
+    mut solver: ResMut<Solver>,
+    button_query: Query<(&Interaction, &Name), (Changed<Interaction>, With<Button>)>,
+    popup_query: Query<Entity, With<Popup>>,
+) {
+    for (interaction, name) in &button_query {
+        if *interaction != Interaction::Pressed
+            || name.to_string() != ButtonType::Solver(solver.kind).to_string()
+        {
+            continue;
+        }
+        for entity in &popup_query {
+            commands.entity(entity).despawn_recursive()
+        }
+
+        let font = asset_server.load(FONT_PATH);
+        let popup = match solver.kind {
+            SolverKind::NTuple => {
+                *solver = Solver::default();
+                new_menu_popup(&font, game.undo_limit, solver.kind)
+            }
+            SolverKind::Expectimax => match NTupleNetwork::load(NTUPLE_FILE) {
+                Ok(network) => {
+                    solver.kind = SolverKind::NTuple;
+                    solver.agent = Arc::new(network);
+                    new_menu_popup(&font, game.undo_limit, solver.kind)
+                }
+                Err(error) => {
+                    new_notice_popup(&font, &format!("Could not load {NTUPLE_FILE}\n{error}"))
+                }
+            },
+        };
+        let popup = spawn_popup(&mut commands, popup, "Menu");
+        commands.entity(popup).insert(Menu);
+    }
+}
+
+/// Starts searching for the best move when H is pressed. The search runs on
+/// the async compute pool, so the game keeps running while it thinks. Only
+/// the classic 4x4 game can be searched, so other boards show a question mark.
+pub fn request_hint(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    grid: Res<Grid>,
+    solver: Res<Solver>,
+    mut game: ResMut<Game>,
+    task_query: Query<&HintTask>,
+    mut arrow_query: Query<&mut Text, With<HintArrow>>,
+) {
+    if !keys.just_pressed(KeyCode::H)
+        || game.state != GameState::Play
+        || grid.deadlocked
+        || !task_query.is_empty()
+    {
+        return;
+    }
+
+    if classic_board(&grid).is_none() || spawn_odds(&grid.spawn_rules).is_none() {
+        for mut text in &mut arrow_query {
+            text.sections[0].value = "?".to_string();
+        }
+        return;
+    }
+    let agent = solver.agent.clone();
+    let position = grid.fork(grid.seed());
+    let task = AsyncComputeTaskPool::get().spawn(async move { agent.best_move(&position) });
+    commands.spawn(HintTask {
+        task,
+        state: grid.state.clone(),
+    });
+    game.hints_used += 1;
+}
+
+/// Shows a finished hint, unless the board has changed since it was asked
+/// for.
+pub fn show_hint(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    mut task_query: Query<(Entity, &mut HintTask)>,
+    mut arrow_query: Query<&mut Text, With<HintArrow>>,
+) {
+    for (entity, mut hint_task) in &mut task_query {
+        if !hint_task.task.is_finished() {
+            continue;
+        }
+        let direction = block_on(&mut hint_task.task);
+        commands.entity(entity).despawn();
+        if hint_task.state != grid.state {
+            continue;
+        }
+
+        let arrow = match direction {
+            Some(MoveDirection::Up) => "\u{2191}",
+            Some(MoveDirection::Right) => "\u{2192}",
+            Some(MoveDirection::Down) => "\u{2193}",
+            Some(MoveDirection::Left) => "\u{2190}",
+            None => "",
+        };
+        for mut text in &mut arrow_query {
+            text.sections[0].value = arrow.to_string();
+        }
+    }
+}
+
+/// Hides the hint once the board changes and resets the hint count for each
+/// new game.
+pub fn clear_hint(
+    mut game: ResMut<Game>,
+    mut arrow_query: Query<&mut Text, With<HintArrow>>,
+    mut grid_updated_event: EventReader<GridUpdatedEvent>,
+    mut new_game_event: EventReader<NewGameEvent>,
+) {
+    if new_game_event.read().count() > 0 {
+        game.hints_used = 0;
+    }
+    if grid_updated_event.read().count() == 0 {
+        return;
+    }
+    for mut text in &mut arrow_query {
+        if !text.sections[0].value.is_empty() {
+            text.sections[0].value.clear();
+        }
+    }
+}
+
+/// Turns autoplay on or off from the menu. The agent only plays the classic
+/// 4x4 game, so other boards get a notice instead.
+pub fn handle_autoplay_button(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    grid: Res<Grid>,
+    mut game: ResMut<Game>,
+    mut autoplay: ResMut<Autoplay>,
+    button_query: Query<(&Interaction, &Name), (Changed<Interaction>, With<Button>)>,
+    popup_query: Query<Entity, With<Popup>>,
+) {
+    for (interaction, name) in &button_query {
+        if *interaction != Interaction::Pressed
+            || name.to_string() != ButtonType::Autoplay.to_string()
+        {
+            continue;
+        }
+        for entity in &popup_query {
+            commands.entity(entity).despawn_recursive()
+        }
+
+        if classic_board(&grid).is_none() {
+            let popup = spawn_popup(
+                &mut commands,
+                new_notice_popup(
+                    &asset_server.load(FONT_PATH),
+                    "Autoplay needs the classic 4x4 board",
+                ),
+                "Autoplay",
+            );
+            commands.entity(popup).insert(Menu);
+            game.state = GameState::Menu;
+            continue;
+        }
+
+        if autoplay.enabled {
+            autoplay.stop();
+        } else {
+            autoplay.start();
+        }
+        game.state = GameState::Play;
+    }
+}
+
+/// Asks the agent for a move at the autoplay rate. The search runs on the
+/// async compute pool like hints do, and the clock pauses with the game.
+pub fn run_autoplay(
+    mut commands: Commands,
+    time: Res<Time>,
+    grid: Res<Grid>,
+    game: Res<Game>,
+    solver: Res<Solver>,
+    mut autoplay: ResMut<Autoplay>,
+    task_query: Query<&AutoplayTask>,
+) {
+    if !autoplay.enabled || game.state != GameState::Play || grid.deadlocked {
+        return;
+    }
+    autoplay.timer.tick(time.delta());
+    if !autoplay.timer.just_finished() || !task_query.is_empty() {
+        return;
+    }
+
+    if classic_board(&grid).is_none() || spawn_odds(&grid.spawn_rules).is_none() {
+        autoplay.stop();
+        return;
+    }
+    let agent = solver.agent.clone();
+    let position = grid.fork(grid.seed());
+    let task = AsyncComputeTaskPool::get().spawn(async move { agent.best_move(&position) });
+    commands.spawn(AutoplayTask {
+        task,
+        state: grid.state.clone(),
+    });
+}
+
+/// Plays the agent's move once the search finishes, as long as the board is
+/// still the one it searched.
+pub fn play_autoplay_move(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    mut autoplay: ResMut<Autoplay>,
+    mut task_query: Query<(Entity, &mut AutoplayTask)>,
+    mut move_event: EventWriter<MoveEvent>,
+) {
+    for (entity, mut autoplay_task) in &mut task_query {
+        if !autoplay_task.task.is_finished() {
+            continue;
+        }
+        let direction = block_on(&mut autoplay_task.task);
+        commands.entity(entity).despawn();
+        if !autoplay.enabled || autoplay_task.state != grid.state {
+            continue;
+        }
+        match direction {
+            Some(direction) => move_event.send(MoveEvent(Move::Square(direction))),
+            None => autoplay.stop(),
+        }
+    }
+}
+
+/// Hands control back to the player when the game ends or Escape is pressed.
+pub fn stop_autoplay(
+    keys: Res<Input<KeyCode>>,
+    mut autoplay: ResMut<Autoplay>,
+    mut gameover_event: EventReader<GameoverEvent>,
+    mut objective_ended_event: EventReader<ObjectiveEndedEvent>,
+) {
+    let game_over = gameover_event.read().count() > 0;
+    let objective_ended = objective_ended_event.read().count() > 0;
+    if autoplay.enabled && (game_over || objective_ended || keys.just_pressed(KeyCode::Escape)) {
+        autoplay.stop();
+    }
+}
+
 #[cfg(feature = "debug-commands")]
 pub fn debug_commands(
     mut evr_char: EventReader<ReceivedCharacter>,
     keys: Res<Input<KeyCode>>,
     mut string: Local<String>,
     mut grid: ResMut<Grid>,
+    mut game: ResMut<Game>,
+    mut grid_updated_event: EventWriter<GridUpdatedEvent>,
+    mut new_game_event: EventWriter<NewGameEvent>,
 ) {
     if keys.just_pressed(KeyCode::Return) {
         println!("{}", &*string);
-        if &*string == "gameover" {
+        if let Some(Ok(seed)) = string.strip_prefix("seed ").map(str::parse::<u64>) {
+            grid.reset_with_seed(seed);
+            game.keep_going = false;
+            game.daily = None;
+            grid_updated_event.send(GridUpdatedEvent());
+            new_game_event.send(NewGameEvent());
+        } else if &*string == "gameover" {
             grid.state = vec![
-                vec![2u32, 4u32, 8u32, 16u32],
-                vec![32u32, 64u32, 128u32, 256u32],
-                vec![512u32, 1024u32, 2048u32, 4096u32],
-                vec![8192u32, 16384u32, 32768u32, 0u32],
+                vec![2, 4, 8, 16],
+                vec![32, 64, 128, 256],
+                vec![512, 1024, 2048, 4096],
+                vec![8192, 16384, 32768, 0],
             ];
         }
         string.clear();